@@ -2,40 +2,72 @@
     This file defines the components and systems commonly used by them.
 */
 use bracket_terminal::prelude::*;
-use hecs::{Entity, World};
+use hecs::{Entity, With, Without, World};
 use serde::{Deserialize, Serialize};
 use std::cmp;
 
 use crate::{
+    breakable::smash,
     combat::{attack, CombatStats},
+    crafting::drop_mined_resources,
     data_read::named_tile,
+    dialogue::Dialogue,
+    equipment::mining_power,
+    faction::{reaction_between, Faction, Reaction},
     fov::ViewShed,
+    item::Item,
     map::{Destructible, Map, TileType},
     messagelog::Message,
     monster::Breed,
+    noise::{Noise, MINE_NOISE, SMASH_NOISE},
+    shop::Shop,
     BTerm, State,
 };
 
 pub enum MoveResult {
-    Moved(String),
+    Moved,
     Attack(Entity),
+    Talk(Entity),
     Mine(Destructible),
     InvalidMove(String),
 }
 
 /// Get's player information and calls bump_tile
 /// Used to make bump_tile entity agnostic ie monster or player can call it
+/// Friendly beings are talked to rather than attacked
 pub fn player_bump(map: &mut Map, world: &mut World, delta: Point) -> MoveResult {
-    if let Some((e, (pos, view, _))) = world.query::<(&mut Position, &mut ViewShed, &Player)>().iter().next() {
-        let dest_tile = match safe_position_delta(pos, delta, Point::new(map.width, map.height)) {
-            Ok(dest_tile) => dest_tile,
-            Err(..) => {
-                return MoveResult::InvalidMove("Cannot move to that position".to_string());
+    let result = match world.query::<(&mut Position, &mut ViewShed, &Player)>().iter().next() {
+        Some((e, (pos, view, _))) => match safe_position_delta(pos, delta, Point::new(map.width, map.height)) {
+            Ok(dest_tile) => bump_tile(map, &dest_tile, pos, view, e),
+            Err(..) => MoveResult::InvalidMove("Cannot move to that position".to_string()),
+        },
+        None => MoveResult::InvalidMove("No player entity found?".to_string()),
+    };
+
+    match result {
+        MoveResult::Attack(target) if is_friendly_to_player(world, target) => {
+            if world.get::<&Dialogue>(target).is_ok() || world.get::<&Shop>(target).is_ok() {
+                return MoveResult::Talk(target);
             }
-        };
-        return bump_tile(map, &dest_tile, pos, view, e);
+            let name = match world.get::<&Breed>(target) {
+                Ok(breed) => breed.name.clone(),
+                Err(..) => "Someone".to_string(),
+            };
+            MoveResult::InvalidMove(format!("{name} is in your way"))
+        }
+        result => result,
+    }
+}
+
+fn is_friendly_to_player(world: &World, target: Entity) -> bool {
+    let player_faction = match world.query::<With<&Faction, &Player>>().iter().next() {
+        Some((_, faction)) => faction.clone(),
+        None => return false,
+    };
+    match world.get::<&Faction>(target) {
+        Ok(faction) => reaction_between(&player_faction, &faction) == Reaction::Friendly,
+        Err(..) => false,
     }
-    MoveResult::InvalidMove("No player entity found?".to_string())
 }
 
 /// Adds the delta to a position while keeping it inside 0 -> upper x or y
@@ -67,6 +99,9 @@ pub fn bump_tile(
     if let Some(target) = map.beings[dest_idx] {
         return MoveResult::Attack(target);
     }
+    if let Some(destructible @ Destructible::Entity(_)) = map.destructibles[dest_idx] {
+        return MoveResult::Mine(destructible);
+    }
 
     if let Some(tile) = map.tiles.get_mut(dest_idx) {
         view.dirty = true; // make it dirty so the vision is updated definitely
@@ -75,7 +110,7 @@ pub fn bump_tile(
             *pos = dest_tile.clone();
             map.beings[idx] = None;
             map.beings[dest_idx] = Some(who);
-            return MoveResult::Moved("".to_string());
+            return MoveResult::Moved;
         } else if let Some(destructible) = map.destructibles[dest_idx] {
             return MoveResult::Mine(destructible);
         }
@@ -88,13 +123,12 @@ pub fn bump_tile(
 pub fn change_floor(world: &mut World, map: &mut Map, delta: i32) -> bool {
     if let Some((_, (pos, view, _))) = world.query::<(&mut Position, &mut ViewShed, &Player)>().iter().next() {
         // positive delta means we try to descend
-        if delta > 0 && map.tiles[pos.0.to_index(map.width)].tile_type == TileType::DownStairs {
-            view.dirty = true;
-            return true;
-        } else if delta < 0 // negative delta means we try to ascend
+        let descending = delta > 0 && map.tiles[pos.0.to_index(map.width)].tile_type == TileType::DownStairs;
+        // negative delta means we try to ascend
+        let ascending = delta < 0
             && map.tiles[pos.0.to_index(map.width)].tile_type == TileType::UpStairs
-            && !(map.depth == 0 && delta == 1)
-        {
+            && !(map.depth == 0 && delta == 1);
+        if descending || ascending {
             view.dirty = true;
             return true;
         }
@@ -102,54 +136,90 @@ pub fn change_floor(world: &mut World, map: &mut Map, delta: i32) -> bool {
     false
 }
 
-pub fn player_attack(world: &mut World, message_log: &mut Vec<Message>, target: Entity, turn_sent: usize) {
+/// The player attacks a target, returns true if the blow was fatal
+pub fn player_attack(world: &mut World, message_log: &mut Vec<Message>, target: Entity, turn_sent: usize) -> bool {
     if let Some((_, (attacker_stats, _))) = world.query::<(&mut CombatStats, &Player)>().iter().next() {
         if let Ok(mut defender) = world.query_one::<(&mut CombatStats, &Breed)>(target) {
             if let Some(defender) = defender.get() {
+                let already_dead = defender.0.health == 0;
                 let damage_stmt = attack((defender.0, &defender.1.name), (attacker_stats, &"Player"));
                 message_log.push(Message::new(damage_stmt, turn_sent));
+                return !already_dead && defender.0.health == 0;
             }
         } // Prevents stale enemies from being double despawned
     }
+    false
 }
 
 /// Attempts to mine a position and returns if successful
-pub fn mine(map: &mut Map, world: &mut World, destructible: Destructible, delta: Point) -> bool {
-    if let Some((_, (pos, view, _))) = world.query::<(&mut Position, &mut ViewShed, &Player)>().iter().next() {
-        let dest_pos = match safe_position_delta(pos, delta, Point::new(map.width, map.height)) {
+pub fn mine(
+    map: &mut Map,
+    world: &mut World,
+    destructible: Destructible,
+    delta: Point,
+    message_log: &mut Vec<Message>,
+    noises: &mut Vec<Noise>,
+    turn_sent: usize,
+) -> bool {
+    let dest_pos = match world.query::<With<&Position, &Player>>().iter().next() {
+        Some((_, pos)) => match safe_position_delta(pos, delta, Point::new(map.width, map.height)) {
             Ok(pos) => pos,
             Err(..) => return false,
-        };
-        let dest_idx = dest_pos.0.to_index(map.width);
-
-        match destructible {
-            Destructible::Tile { max_hp, mut hp } => {
-                hp -= 1;
-                if hp <= 0 {
-                    map.tiles[dest_idx] = match map.tiles[dest_idx].tile_type {
-                        TileType::Wall => named_tile("Grass Floor"),
-                        _ => named_tile("Grass Floor"),
-                    };
-                    map.destructibles[dest_idx] = None;
-                    return true;
+        },
+        None => return false,
+    };
+    let dest_idx = dest_pos.0.to_index(map.width);
+
+    match destructible {
+        Destructible::Tile { max_hp, hp } => {
+            let hp = hp.saturating_sub(mining_power(world));
+            if hp == 0 {
+                if map.tiles[dest_idx].tile_type == TileType::Wall {
+                    drop_mined_resources(world, map.depth, dest_pos.0);
                 }
+                map.tiles[dest_idx] = match map.tiles[dest_idx].tile_type {
+                    TileType::Wall => named_tile("Grass Floor"),
+                    _ => named_tile("Grass Floor"),
+                };
+                map.destructibles[dest_idx] = None;
+                map.excavated[dest_idx] = true;
+                map.air_dirty = true;
+            } else {
                 map.destructibles[dest_idx] = Some(Destructible::Tile { max_hp, hp });
             }
-            Destructible::Entity(_e) => {
-                // if let Some(e) = world.query_one::<()>()
+            // every strike of the pick rings through the rock, beings with keen hearing will come to look
+            noises.push(Noise::new(dest_pos.0, MINE_NOISE));
+        }
+        Destructible::Entity(e) => {
+            if let Some(name) = smash(world, map, e) {
+                message_log.push(Message::new(format!("You smashed the {name}"), turn_sent));
             }
+            noises.push(Noise::new(dest_pos.0, SMASH_NOISE));
         }
+    }
 
+    if let Some((_, view)) = world.query_mut::<With<&mut ViewShed, &Player>>().into_iter().next() {
         view.dirty = true;
-        return true;
     }
-
-    false
+    true
 }
 
 /// Renders all entities that have a Position and Sprite component
+/// Items are drawn first so anything standing on top of them is still seen
 pub fn render_entities(ctx: &mut BTerm, state: &State) {
-    for (_, (pos, sprite)) in state.world.query::<(&Position, &CharSprite)>().iter() {
+    for (_, (pos, sprite)) in state.world.query::<With<(&Position, &CharSprite), &Item>>().iter() {
+        if state.visible[pos.0.to_index(state.map.width)] || state.config.dev_mode {
+            ctx.set(pos.x(), pos.y(), sprite.fg, sprite.bg, sprite.glyph);
+        }
+    }
+    for (_, (pos, sprite, breed)) in state
+        .world
+        .query::<Without<(&Position, &CharSprite, Option<&Breed>), &Item>>()
+        .iter()
+    {
+        if breed.is_some_and(|breed| breed.is_concealed()) && !state.config.dev_mode {
+            continue;
+        }
         if state.visible[pos.0.to_index(state.map.width)] || state.config.dev_mode {
             ctx.set(pos.x(), pos.y(), sprite.fg, sprite.bg, sprite.glyph);
         }
@@ -160,9 +230,6 @@ pub fn render_entities(ctx: &mut BTerm, state: &State) {
 #[derive(Deserialize, Debug)]
 pub struct Player;
 
-#[derive(Deserialize, Debug)]
-pub struct Name(pub String);
-
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Position(pub Point);
 
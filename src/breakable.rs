@@ -0,0 +1,78 @@
+/*  Breakables are static entities that get in the way, like crates or crystal formations.
+    They are stored in the map's destructibles so they block movement until they are smashed.
+*/
+use hecs::{Entity, World};
+use rand::Rng;
+
+use crate::{
+    actor::Position,
+    data_read::{named_breakable_builder, named_item_builder, ENTITY_DB},
+    map::{Destructible, Map},
+};
+
+pub struct Breakable {
+    pub name: String,
+    pub hp: usize,
+    pub drops: Vec<Drop>,
+}
+
+/// An item with a chance of being left behind when a breakable is destroyed, chance is 0.0 to 1.0
+pub struct Drop {
+    pub item: String,
+    pub chance: f32,
+}
+
+impl Breakable {
+    pub fn new(name: impl ToString, hp: usize, drops: Vec<Drop>) -> Self {
+        Self {
+            name: name.to_string(),
+            hp,
+            drops,
+        }
+    }
+}
+
+/// Spawns a breakable on a tile and marks the tile as destructible
+pub fn spawn_breakable(world: &mut World, map: &mut Map, name: &str, pos: Position) -> Option<Entity> {
+    let idx = pos.0.to_index(map.width);
+    let mut eb = named_breakable_builder(&ENTITY_DB.lock().unwrap(), name, pos)?;
+    let e = world.spawn(eb.build());
+    map.destructibles[idx] = Some(Destructible::Entity(e));
+    Some(e)
+}
+
+/// Hits a breakable once, when it runs out of health it is destroyed and its drops are scattered on its tile
+/// Returns the name of the breakable if it was destroyed
+pub fn smash(world: &mut World, map: &mut Map, target: Entity) -> Option<String> {
+    let (pos, name, drops) = {
+        let mut query = world.query_one::<(&mut Breakable, &Position)>(target).ok()?;
+        let (breakable, pos) = query.get()?;
+        breakable.hp = breakable.hp.saturating_sub(1);
+        if breakable.hp > 0 {
+            return None;
+        }
+
+        let mut rng = rand::thread_rng();
+        let drops: Vec<String> = breakable
+            .drops
+            .iter()
+            .filter(|drop| rng.gen::<f32>() < drop.chance)
+            .map(|drop| drop.item.clone())
+            .collect();
+        (pos.clone(), breakable.name.clone(), drops)
+    };
+
+    if world.despawn(target).is_err() {
+        println!("Out of sync, couldn't destroy breakable");
+    }
+    map.destructibles[pos.0.to_index(map.width)] = None;
+
+    for drop in drops {
+        let builder = named_item_builder(&ENTITY_DB.lock().unwrap(), &drop, pos.clone());
+        if let Some(mut eb) = builder {
+            world.spawn(eb.build());
+        }
+    }
+
+    Some(name)
+}
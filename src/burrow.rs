@@ -0,0 +1,66 @@
+/*  Burrowing beings treat diggable walls as slow ground rather than an obstacle.
+    They dig out the walls as they pass, changing the cave for good.
+*/
+use bracket_pathfinding::prelude::{Algorithm2D, BaseMap, DistanceAlg, SmallVec};
+use bracket_terminal::prelude::Point;
+
+use crate::{
+    data_read::named_tile,
+    map::{Destructible, Map},
+};
+
+/// Tag Component for beings that are able to dig through walls
+pub struct Burrower;
+
+/// Digging through rock takes a few steps worth of effort
+const DIG_PATH_COST: f32 = 3.0;
+
+/// A view of the map as a burrower sees it
+pub struct BurrowMap<'a>(pub &'a Map);
+
+/// Walls that could be mined by the player can also be dug by a burrower
+pub fn is_diggable(map: &Map, idx: usize) -> bool {
+    matches!(map.destructibles.get(idx), Some(Some(Destructible::Tile { .. })))
+}
+
+/// Turns a diggable wall into open floor, burrows weaken the ceiling just like the player's tunnels
+pub fn dig(map: &mut Map, idx: usize) {
+    map.tiles[idx] = named_tile("Stone Floor");
+    map.destructibles[idx] = None;
+    map.excavated[idx] = true;
+    map.air_dirty = true;
+}
+
+impl BaseMap for BurrowMap<'_> {
+    fn is_opaque(&self, idx: usize) -> bool {
+        self.0.is_opaque(idx)
+    }
+
+    fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
+        let mut exits = SmallVec::new();
+        let location = self.0.idx_to_point(idx);
+
+        for delta in [Point::new(-1, 0), Point::new(1, 0), Point::new(0, -1), Point::new(0, 1)] {
+            if let Some(idx) = self.0.valid_exit(location, delta) {
+                exits.push((idx, 1.0));
+                continue;
+            }
+            let dest = location + delta;
+            if self.0.within_bounds(dest) && is_diggable(self.0, dest.to_index(self.0.width)) {
+                exits.push((dest.to_index(self.0.width), DIG_PATH_COST));
+            }
+        }
+
+        exits
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        DistanceAlg::Pythagoras.distance2d(self.0.idx_to_point(idx1), self.0.idx_to_point(idx2))
+    }
+}
+
+impl Algorithm2D for BurrowMap<'_> {
+    fn dimensions(&self) -> Point {
+        Point::new(self.0.width, self.0.height)
+    }
+}
@@ -1,10 +1,31 @@
-use hecs::{Entity, World};
-use std::cmp::max;
+use bracket_random::prelude::RandomNumberGenerator;
+use hecs::{Entity, Without, World};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{cmp::max, sync::Mutex};
 
-use crate::{actor::Position, map::Map};
+use crate::{
+    actor::{Player, Position},
+    map::Map,
+};
+
+lazy_static! {
+    /// Every attack rolls from here so a run's fights can be replayed from its seed
+    static ref COMBAT_RNG: Mutex<RandomNumberGenerator> = Mutex::new(RandomNumberGenerator::new());
+}
+
+/// Chance out of 100 for an evenly matched attacker to land a blow
+const BASE_HIT_CHANCE: i32 = 75;
+/// Each point of strength over the defender's defense changes the hit chance by this much
+const HIT_CHANCE_PER_POINT: i32 = 5;
+const MIN_HIT_CHANCE: i32 = 10;
+const MAX_HIT_CHANCE: i32 = 95;
+/// Rolls at or under this out of 100 are critical hits, these always land
+const CRIT_CHANCE: i32 = 5;
+const CRIT_MULTIPLIER: u32 = 2;
 
 /// Stats that are used for damage calculation
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct CombatStats {
     pub health: u32,
     pub max_health: u32,
@@ -23,25 +44,86 @@ impl CombatStats {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttackOutcome {
+    Miss,
+    Hit(u32),
+    Critical(u32),
+}
+
+/// Reseeds the rng used for attacks, done at the start of each run
+pub fn seed_combat_rng(seed: u64) {
+    *COMBAT_RNG.lock().unwrap() = RandomNumberGenerator::seeded(seed);
+}
+
 /// Attacks a defender by modifying health components
 pub fn attack(
     (defender, d_name): (&mut CombatStats, &impl ToString),
     (attacker, a_name): (&CombatStats, &impl ToString),
 ) -> String {
-    let damage_given = max(0, (attacker.strength - defender.defense).abs());
-    let new_hp = defender.health.saturating_sub(damage_given as u32);
-    defender.health = new_hp;
-    format!(
-        "{0} took {damage_given} hp from {1}",
-        d_name.to_string(),
-        a_name.to_string()
-    )
+    let outcome = resolve_attack(&mut COMBAT_RNG.lock().unwrap(), attacker, defender);
+    let (d_name, a_name) = (d_name.to_string(), a_name.to_string());
+    match outcome {
+        AttackOutcome::Miss => format!("{a_name} missed {d_name}"),
+        AttackOutcome::Hit(damage_given) => {
+            defender.health = defender.health.saturating_sub(damage_given);
+            format!("{d_name} took {damage_given} hp from {a_name}")
+        }
+        AttackOutcome::Critical(damage_given) => {
+            defender.health = defender.health.saturating_sub(damage_given);
+            format!("{a_name} critically hit {d_name} for {damage_given} hp!")
+        }
+    }
+}
+
+/// Rolls to hit then for damage, neither of the stats are modified
+pub fn resolve_attack(
+    rng: &mut RandomNumberGenerator,
+    attacker: &CombatStats,
+    defender: &CombatStats,
+) -> AttackOutcome {
+    let roll = rng.roll_dice(1, 100);
+    let critical = roll <= CRIT_CHANCE;
+    if !critical && roll > hit_chance(attacker, defender) {
+        return AttackOutcome::Miss;
+    }
+
+    let (min_damage, max_damage) = damage_range(attacker);
+    let mut damage = rng.range(min_damage, max_damage + 1);
+    if critical {
+        damage *= CRIT_MULTIPLIER;
+    }
+    let damage = mitigate(damage, defender.defense);
+
+    match critical {
+        true => AttackOutcome::Critical(damage),
+        false => AttackOutcome::Hit(damage),
+    }
+}
+
+/// Chance out of 100 for an attack to land
+pub fn hit_chance(attacker: &CombatStats, defender: &CombatStats) -> i32 {
+    let chance = BASE_HIT_CHANCE + HIT_CHANCE_PER_POINT * (attacker.strength - defender.defense);
+    chance.clamp(MIN_HIT_CHANCE, MAX_HIT_CHANCE)
+}
+
+/// The lowest and highest damage an attacker can roll before armor, strength is the top of the range
+pub fn damage_range(attacker: &CombatStats) -> (u32, u32) {
+    let strength = max(1, attacker.strength) as u32;
+    (max(1, strength / 2), strength)
+}
+
+/// Armor soaks up half its value in damage but a landed blow always does at least 1
+pub fn mitigate(damage: u32, defense: i32) -> u32 {
+    let soaked = max(0, defense) as u32 / 2;
+    max(1, damage.saturating_sub(soaked))
 }
 
 /// Iterates all beings with health and if health is 0 then it is destroyed
+/// The player is left alone as their death ends the game instead
 pub fn destroy_dead_beings(world: &mut World, map: &mut Map) {
     let mut entities_to_destroy: Vec<(Entity, Position)> = vec![];
-    for (entity, (stats, pos)) in world.query::<(&CombatStats, &Position)>().iter() {
+    for (entity, (stats, pos)) in world.query::<Without<(&CombatStats, &Position), &Player>>().iter() {
         if stats.health == 0 {
             entities_to_destroy.push((entity, pos.clone()));
         }
@@ -55,3 +137,79 @@ pub fn destroy_dead_beings(world: &mut World, map: &mut Map) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roll_many(seed: u64, attacker: &CombatStats, defender: &CombatStats) -> Vec<AttackOutcome> {
+        let mut rng = RandomNumberGenerator::seeded(seed);
+        (0..1000)
+            .map(|_| resolve_attack(&mut rng, attacker, defender))
+            .collect()
+    }
+
+    fn total_damage(outcomes: &[AttackOutcome]) -> u32 {
+        outcomes
+            .iter()
+            .map(|outcome| match outcome {
+                AttackOutcome::Miss => 0,
+                AttackOutcome::Hit(dmg) | AttackOutcome::Critical(dmg) => *dmg,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn same_seed_same_fight() {
+        let attacker = CombatStats::new(10, 6, 1);
+        let defender = CombatStats::new(10, 2, 2);
+        assert_eq!(roll_many(7, &attacker, &defender), roll_many(7, &attacker, &defender));
+    }
+
+    #[test]
+    fn hit_chance_is_clamped() {
+        let weak = CombatStats::new(10, 1, 0);
+        let strong = CombatStats::new(10, 50, 50);
+        assert_eq!(hit_chance(&weak, &strong), MIN_HIT_CHANCE);
+        assert_eq!(hit_chance(&strong, &weak), MAX_HIT_CHANCE);
+        assert_eq!(hit_chance(&weak, &CombatStats::new(10, 1, 1)), BASE_HIT_CHANCE);
+    }
+
+    #[test]
+    fn damage_stays_in_range() {
+        let attacker = CombatStats::new(10, 8, 0);
+        let defender = CombatStats::new(10, 0, 0);
+        for outcome in roll_many(1, &attacker, &defender) {
+            match outcome {
+                AttackOutcome::Miss => {}
+                AttackOutcome::Hit(dmg) => assert!((4..=8).contains(&dmg)),
+                AttackOutcome::Critical(dmg) => assert!((8..=16).contains(&dmg)),
+            }
+        }
+    }
+
+    #[test]
+    fn attacks_miss_and_crit_sometimes() {
+        let attacker = CombatStats::new(10, 2, 0);
+        let defender = CombatStats::new(10, 0, 2);
+        let outcomes = roll_many(3, &attacker, &defender);
+        assert!(outcomes.contains(&AttackOutcome::Miss));
+        assert!(outcomes.iter().any(|o| matches!(o, AttackOutcome::Critical(_))));
+        assert!(outcomes.iter().any(|o| matches!(o, AttackOutcome::Hit(_))));
+    }
+
+    #[test]
+    fn more_defense_means_less_damage() {
+        let attacker = CombatStats::new(10, 6, 0);
+        let unarmored = CombatStats::new(10, 0, 0);
+        let armored = CombatStats::new(10, 0, 6);
+        assert!(total_damage(&roll_many(5, &attacker, &armored)) < total_damage(&roll_many(5, &attacker, &unarmored)));
+    }
+
+    #[test]
+    fn landed_blows_always_hurt() {
+        assert_eq!(mitigate(1, 40), 1);
+        assert_eq!(mitigate(6, 4), 4);
+        assert_eq!(mitigate(6, -3), 6);
+    }
+}
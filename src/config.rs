@@ -11,4 +11,6 @@ pub struct Config {
     pub map_x: usize,
     pub map_y: usize,
     pub world_seed: u64,
+    #[serde(default)]
+    pub town_depths: Vec<usize>, // depths that are underground towns rather than caves
 }
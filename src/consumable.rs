@@ -0,0 +1,134 @@
+/*  Consumables are the items the player uses up for their effects, like potions, bombs and maps.
+    The player picks what to use from a menu of everything they carry that does something, anything meant to be
+    thrown is aimed first. Gear is put on from the same menu.
+*/
+use bracket_terminal::prelude::*;
+use hecs::{With, World};
+
+use crate::{
+    actor::{Player, Position},
+    data_read::{item_effects, item_gear, item_ranged},
+    effects::apply_effects,
+    energy::USE_COST,
+    equipment::{equip, Equipment},
+    item::{Inventory, Item},
+    messagelog::Message,
+    ranged::aim,
+    state::{PlayerResponse, RunState, State},
+};
+
+/// Where the player is in the list of things they can use
+#[derive(Clone)]
+pub struct UseMenu {
+    pub selected: usize,
+}
+
+/// Opens the use menu, None if the player carries nothing usable
+pub fn open_use_menu(state: &mut State) -> Option<RunState> {
+    if usable_items(&state.world).is_empty() {
+        state
+            .message_log
+            .push(Message::new("You have nothing to use".to_string(), state.turn_counter));
+        return None;
+    }
+    Some(RunState::UseItem(UseMenu { selected: 0 }))
+}
+
+/// Lets the player pick something to use until they do or back out
+pub fn run_use_menu_systems(state: &mut State, ctx: &mut BTerm, mut menu: UseMenu) -> PlayerResponse {
+    let usable = usable_items(&state.world);
+    if let Some(key) = ctx.key {
+        match key {
+            VirtualKeyCode::Escape => return PlayerResponse::StateChange(RunState::InGame),
+            VirtualKeyCode::K | VirtualKeyCode::Up => menu.selected = menu.selected.saturating_sub(1),
+            VirtualKeyCode::J | VirtualKeyCode::Down => {
+                menu.selected = (menu.selected + 1).min(usable.len().saturating_sub(1));
+            }
+            VirtualKeyCode::Return => {
+                if let Some((name, _)) = usable.get(menu.selected) {
+                    return use_item(state, name);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    draw_use_menu(ctx, state, &usable, &menu);
+    PlayerResponse::StateChange(RunState::UseItem(menu))
+}
+
+/// Every item the player carries that does something when used, alongside how many of each
+fn usable_items(world: &World) -> Vec<(String, usize)> {
+    match world.query::<With<&Inventory, &Player>>().iter().next() {
+        Some((_, inventory)) => inventory
+            .counts()
+            .into_iter()
+            .filter(|(name, _)| item_effects(name).is_some() || item_gear(name).is_some())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Uses up an item on the player, thrown items are aimed instead and used up once they are thrown
+/// Gear is equipped rather than used up
+fn use_item(state: &mut State, name: &str) -> PlayerResponse {
+    if item_gear(name).is_some() {
+        return match equip(state, name) {
+            true => PlayerResponse::TurnAdvance(USE_COST),
+            false => PlayerResponse::StateChange(RunState::InGame),
+        };
+    }
+    let Some((_, (pos, inventory))) = state
+        .world
+        .query_mut::<With<(&Position, &mut Inventory), &Player>>()
+        .into_iter()
+        .next()
+    else {
+        return PlayerResponse::StateChange(RunState::InGame);
+    };
+    let player_pos = pos.0;
+
+    if item_ranged(name).is_some() {
+        return PlayerResponse::StateChange(aim(state, name.to_string(), player_pos));
+    }
+    let Some(idx) = inventory.items.iter().position(|item| item.name == name) else {
+        return PlayerResponse::StateChange(RunState::InGame);
+    };
+    inventory.items.remove(idx);
+
+    state
+        .message_log
+        .push(Message::new(format!("You use the {name}"), state.turn_counter));
+    if let Some(effects) = item_effects(name) {
+        apply_effects(state, &effects, player_pos);
+    }
+    PlayerResponse::TurnAdvance(USE_COST)
+}
+
+fn draw_use_menu(ctx: &mut BTerm, state: &State, usable: &[(String, usize)], menu: &UseMenu) {
+    let (x, y, width, height) = (25, 12, 50, 30);
+    ctx.draw_box(x, y, width, height, WHITE, BLACK);
+    ctx.print_color(x + 2, y, YELLOW, BLACK, " Use ");
+
+    if let Some((_, equipment)) = state.world.query::<With<&Equipment, &Player>>().iter().next() {
+        let worn = |item: &Option<Item>| item.as_ref().map_or("Nothing".to_string(), |item| item.name.clone());
+        ctx.print_color(
+            x + 2,
+            y + 2,
+            GREY,
+            BLACK,
+            format!("Wielding: {}", worn(&equipment.hand)),
+        );
+        ctx.print_color(x + 2, y + 3, GREY, BLACK, format!("Wearing: {}", worn(&equipment.body)));
+    }
+
+    for (offset, (name, count)) in usable.iter().enumerate().take(height - 7) {
+        let color = match offset == menu.selected {
+            true => LIGHT_BLUE,
+            false => WHITE,
+        };
+        ctx.print_color(x + 2, y + 5 + offset, color, BLACK, format!("{count}x {name}"));
+    }
+
+    ctx.print_color(x + 2, y + height, GREY, BLACK, " Enter use  Esc back ");
+}
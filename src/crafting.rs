@@ -0,0 +1,264 @@
+/*  Crafting turns what the player mines, smashes and forages into something more useful.
+    Recipes are read from data, some can be put together anywhere while others need a workbench or forge
+    within reach. Mining out rock now and then turns up stone and ore to craft with.
+*/
+use bracket_pathfinding::prelude::{DistanceAlg, Point};
+use bracket_terminal::prelude::*;
+use hecs::{With, World};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    actor::{Player, Position},
+    breakable::spawn_breakable,
+    data_read::{named_item_builder, recipes, Recipe, ENTITY_DB},
+    energy::CRAFT_COST,
+    item::{Inventory, Item},
+    map::{Map, TileType},
+    messagelog::Message,
+    state::{PlayerResponse, RunState, State},
+};
+
+/// Chance of a mined out wall leaving a stone behind
+const STONE_CHANCE: f32 = 0.35;
+/// Chance of finding ore in a mined out wall at the surface and how much it grows every floor down
+const ORE_CHANCE: f32 = 0.05;
+const ORE_CHANCE_PER_DEPTH: f32 = 0.02;
+const MAX_ORE_CHANCE: f32 = 0.3;
+/// Chance of a cave floor having a workbench left in it
+const WORKBENCH_CHANCE: f32 = 0.3;
+/// Forges are only found this deep and not on every floor
+const FORGE_DEPTH: usize = 4;
+const FORGE_CHANCE: f32 = 0.2;
+
+/// A workbench, forge or other fixture some recipes need to be made at
+pub struct Station(pub String);
+
+/// Where the player is in the list of recipes
+#[derive(Clone)]
+pub struct CraftMenu {
+    pub selected: usize,
+}
+
+pub fn open_crafting() -> RunState {
+    RunState::Crafting(CraftMenu { selected: 0 })
+}
+
+/// Lets the player look through the recipes and craft until they back out
+pub fn run_crafting_systems(state: &mut State, ctx: &mut BTerm, mut menu: CraftMenu) -> PlayerResponse {
+    let recipes = recipes();
+    if let Some(key) = ctx.key {
+        match key {
+            VirtualKeyCode::Escape => return PlayerResponse::StateChange(RunState::InGame),
+            VirtualKeyCode::K | VirtualKeyCode::Up => menu.selected = menu.selected.saturating_sub(1),
+            VirtualKeyCode::J | VirtualKeyCode::Down => {
+                menu.selected = (menu.selected + 1).min(recipes.len().saturating_sub(1));
+            }
+            VirtualKeyCode::Return => {
+                if let Some(recipe) = recipes.get(menu.selected) {
+                    if craft(state, recipe) {
+                        return PlayerResponse::TurnAdvance(CRAFT_COST);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    draw_crafting(ctx, state, &recipes, &menu);
+    PlayerResponse::StateChange(RunState::Crafting(menu))
+}
+
+/// Whether the ingredients carried and the stations nearby are enough to make a recipe
+pub fn can_craft(recipe: &Recipe, carried: &[(String, usize)], stations: &[String]) -> bool {
+    let has_station = match &recipe.station {
+        Some(station) => stations.contains(station),
+        None => true,
+    };
+    has_station
+        && recipe.ingredients.iter().all(|ingredient| {
+            carried
+                .iter()
+                .any(|(name, count)| *name == ingredient.item && *count >= ingredient.count)
+        })
+}
+
+/// The names of every station next to the player
+fn stations_in_reach(world: &World) -> Vec<String> {
+    let player_pos = match world.query::<With<&Position, &Player>>().iter().next() {
+        Some((_, pos)) => pos.0,
+        None => return Vec::new(),
+    };
+    world
+        .query::<(&Station, &Position)>()
+        .iter()
+        .filter(|(_, (_, pos))| DistanceAlg::Pythagoras.distance2d(player_pos, pos.0) < 1.5)
+        .map(|(_, (station, _))| station.0.clone())
+        .collect()
+}
+
+fn carried_items(world: &World) -> Vec<(String, usize)> {
+    match world.query::<With<&Inventory, &Player>>().iter().next() {
+        Some((_, inventory)) => inventory.counts(),
+        None => Vec::new(),
+    }
+}
+
+/// Uses up the ingredients of a recipe to make its item, returns true if it was made
+fn craft(state: &mut State, recipe: &Recipe) -> bool {
+    let turn = state.turn_counter;
+    let stations = stations_in_reach(&state.world);
+    if !can_craft(recipe, &carried_items(&state.world), &stations) {
+        let reason = match &recipe.station {
+            Some(station) if !stations.contains(station) => format!("You need a {station} to make that"),
+            _ => "You are missing something to make that".to_string(),
+        };
+        state.message_log.push(Message::new(reason, turn));
+        return false;
+    }
+
+    if let Some((_, inventory)) = state
+        .world
+        .query_mut::<With<&mut Inventory, &Player>>()
+        .into_iter()
+        .next()
+    {
+        for ingredient in recipe.ingredients.iter() {
+            for _ in 0..ingredient.count {
+                if let Some(idx) = inventory.items.iter().position(|item| item.name == ingredient.item) {
+                    inventory.items.remove(idx);
+                }
+            }
+        }
+        for _ in 0..recipe.count {
+            inventory.items.push(Item::new(&recipe.name));
+        }
+    }
+    let made = match recipe.count {
+        1 => recipe.name.clone(),
+        n => format!("{n}x {}", recipe.name),
+    };
+    state.message_log.push(Message::new(format!("You craft {made}"), turn));
+    true
+}
+
+/// Rolls for the stone and ore left behind when a wall is mined out, ore gets more common deeper down
+pub fn mined_resources(depth: usize, rng: &mut impl Rng) -> Vec<&'static str> {
+    let mut found = Vec::new();
+    if rng.gen::<f32>() < STONE_CHANCE {
+        found.push("Stone");
+    }
+    let ore_chance = (ORE_CHANCE + depth as f32 * ORE_CHANCE_PER_DEPTH).min(MAX_ORE_CHANCE);
+    if rng.gen::<f32>() < ore_chance {
+        found.push("Iron Ore");
+    }
+    found
+}
+
+/// Scatters whatever turns up in a mined out wall on its tile
+pub fn drop_mined_resources(world: &mut World, depth: usize, at: Point) {
+    for name in mined_resources(depth, &mut rand::thread_rng()) {
+        let builder = named_item_builder(&ENTITY_DB.lock().unwrap(), name, Position(at));
+        if let Some(mut eb) = builder {
+            world.spawn(eb.build());
+        }
+    }
+}
+
+/// Now and then leaves a workbench or forge somewhere in the caves
+pub fn place_stations(world: &mut World, map: &mut Map) {
+    let mut rng = rand::thread_rng();
+    let mut stations = Vec::new();
+    if rng.gen::<f32>() < WORKBENCH_CHANCE {
+        stations.push("Workbench");
+    }
+    if map.depth >= FORGE_DEPTH && rng.gen::<f32>() < FORGE_CHANCE {
+        stations.push("Forge");
+    }
+
+    for name in stations {
+        let Some(spot) = map.rooms.choose(&mut rng).map(|room| room.get_random_point()) else {
+            return;
+        };
+        let idx = spot.to_index(map.width);
+        if map.tiles[idx].tile_type == TileType::Floor && map.destructibles[idx].is_none() && map.beings[idx].is_none()
+        {
+            spawn_breakable(world, map, name, Position(spot));
+        }
+    }
+}
+
+fn draw_crafting(ctx: &mut BTerm, state: &State, recipes: &[Recipe], menu: &CraftMenu) {
+    let (x, y, width, height) = (20, 10, 60, 34);
+    let carried = carried_items(&state.world);
+    let stations = stations_in_reach(&state.world);
+
+    ctx.draw_box(x, y, width, height, WHITE, BLACK);
+    ctx.print_color(x + 2, y, YELLOW, BLACK, " Crafting ");
+    if !stations.is_empty() {
+        ctx.print_color(x + 30, y, YELLOW, BLACK, format!(" At a {} ", stations.join(", ")));
+    }
+
+    let list_height = height - 10;
+    let scroll = menu.selected.saturating_sub(list_height - 1);
+    for (offset, recipe) in recipes.iter().enumerate().skip(scroll).take(list_height) {
+        let color = match (offset == menu.selected, can_craft(recipe, &carried, &stations)) {
+            (true, _) => LIGHT_BLUE,
+            (false, true) => WHITE,
+            (false, false) => GREY,
+        };
+        let made = match recipe.count {
+            1 => recipe.name.clone(),
+            n => format!("{n}x {}", recipe.name),
+        };
+        ctx.print_color(x + 2, y + 2 + offset - scroll, color, BLACK, made);
+        if let Some(station) = &recipe.station {
+            ctx.print_color(x + 40, y + 2 + offset - scroll, color, BLACK, station);
+        }
+    }
+
+    if let Some(recipe) = recipes.get(menu.selected) {
+        let needs = recipe
+            .ingredients
+            .iter()
+            .map(|ingredient| format!("{}x {}", ingredient.count, ingredient.item))
+            .collect::<Vec<String>>()
+            .join(", ");
+        ctx.print_color(x + 2, y + height - 6, WHITE, BLACK, "Needs:");
+        ctx.print_color(x + 2, y + height - 5, WHITE, BLACK, needs);
+    }
+
+    ctx.print_color(x + 2, y + height, GREY, BLACK, " Enter craft  Esc back ");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn torch_recipe(station: Option<&str>) -> Recipe {
+        serde_json::from_str(&format!(
+            r#"{{ "name": "Torch", "ingredients": [{{ "item": "Wood Scraps", "count": 2 }}], "station": {} }}"#,
+            match station {
+                Some(station) => format!("\"{station}\""),
+                None => "null".to_string(),
+            }
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn crafting_needs_every_ingredient() {
+        let recipe = torch_recipe(None);
+        assert!(can_craft(&recipe, &[("Wood Scraps".to_string(), 2)], &[]));
+        assert!(!can_craft(&recipe, &[("Wood Scraps".to_string(), 1)], &[]));
+        assert!(!can_craft(&recipe, &[], &[]));
+    }
+
+    #[test]
+    fn some_recipes_need_a_station() {
+        let recipe = torch_recipe(Some("Workbench"));
+        let carried = [("Wood Scraps".to_string(), 2)];
+        assert!(!can_craft(&recipe, &carried, &[]));
+        assert!(!can_craft(&recipe, &carried, &["Forge".to_string()]));
+        assert!(can_craft(&recipe, &carried, &["Workbench".to_string()]));
+    }
+}
@@ -0,0 +1,24 @@
+use serde::Deserialize;
+
+use super::living_structs::RawSprite;
+
+#[derive(Deserialize, Debug, Default)]
+pub struct BreakableData {
+    pub all: Vec<RawBreakable>,
+}
+
+/// Static objects like furniture or crystals that block the way until they are smashed
+#[derive(Deserialize, Debug)]
+pub struct RawBreakable {
+    pub name: String,
+    pub sprite: Option<RawSprite>,
+    pub hp: usize,
+    pub drops: Option<Vec<RawDrop>>,
+    pub station: Option<bool>, // whether it can be crafted at
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RawDrop {
+    pub item: String,
+    pub chance: f32,
+}
@@ -0,0 +1,28 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Default)]
+pub struct DialogueData {
+    pub all: Vec<RawDialogue>,
+}
+
+/// A whole conversation, talking starts at the `start` node and follows the options chosen
+#[derive(Deserialize, Debug)]
+pub struct RawDialogue {
+    pub name: String,
+    pub start: String,
+    pub nodes: Vec<DialogueNode>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DialogueNode {
+    pub id: String,
+    pub text: String,
+    pub options: Vec<DialogueOption>,
+}
+
+/// A reply the player can give, the conversation ends if there is no next node
+#[derive(Deserialize, Debug, Clone)]
+pub struct DialogueOption {
+    pub text: String,
+    pub next: Option<String>,
+}
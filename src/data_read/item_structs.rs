@@ -0,0 +1,32 @@
+use serde::Deserialize;
+
+use crate::{effects::Effect, equipment::Gear};
+
+use super::living_structs::RawSprite;
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ItemData {
+    pub all: Vec<RawItem>,
+}
+
+/// Anything that can be picked up and carried around by the player
+#[derive(Deserialize, Debug)]
+pub struct RawItem {
+    pub name: String,
+    pub sprite: Option<RawSprite>,
+    pub value: Option<u32>, // coins it is worth in a shop
+    pub food: Option<u32>,  // how filling it is to eat
+    pub fuel: Option<u32>,  // turns it keeps a torch burning
+    pub ranged: Option<RangedData>,
+    pub effects: Option<Vec<Effect>>, // what happens when it is used, or where it lands when thrown
+    pub gear: Option<Gear>,           // the bonus it gives while equipped
+    pub heavy: Option<bool>,          // too heavy to swim with
+}
+
+/// How an item can be thrown or shot at something
+#[derive(Deserialize, Debug, Clone)]
+pub struct RangedData {
+    pub range: u32,
+    pub power: i32,           // strength of the shot, used in place of the thrower's own
+    pub ammo: Option<String>, // launchers fire this item rather than themselves
+}
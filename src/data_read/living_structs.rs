@@ -1,8 +1,21 @@
+use std::collections::HashMap;
+
 use serde::Deserialize;
 
+use crate::{faction::Reaction, status::OnHit, water::WaterAffinity};
+
 #[derive(Deserialize, Debug, Default)]
 pub struct LivingData {
     pub all: Vec<Living>,
+    #[serde(default)]
+    pub factions: Vec<FactionData>,
+}
+
+/// How one faction reacts to the others, any faction left out is treated as neutral
+#[derive(Deserialize, Debug)]
+pub struct FactionData {
+    pub name: String,
+    pub reactions: HashMap<String, Reaction>,
 }
 
 /// Used for regular living entities such as monsters, humans, good or bad, anything living that is
@@ -16,6 +29,16 @@ pub struct Living {
     pub ai: Option<String>,
     pub player: Option<String>,
     pub combatstats: Option<CombatStats>,
+    pub xp: Option<u32>,
+    pub speed: Option<u32>,
+    pub burrows: Option<bool>,
+    pub hearing: Option<u32>,
+    pub faction: Option<String>,
+    pub dialogue: Option<String>,
+    pub shop: Option<Vec<String>>,
+    pub light: Option<RawLight>,
+    pub on_hit: Option<Vec<OnHit>>,
+    pub water: Option<WaterAffinity>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -25,6 +48,13 @@ pub struct RawSprite {
     pub bg: String,
 }
 
+/// Light given off by a tile or being, color is a hex code
+#[derive(Deserialize, Debug)]
+pub struct RawLight {
+    pub radius: u32,
+    pub color: String,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct CombatStats {
     pub hp: u32,
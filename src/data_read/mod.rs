@@ -1,4 +1,4 @@
-use bracket_terminal::prelude::{PURPLE, RGB, WHITESMOKE};
+use bracket_terminal::prelude::{PURPLE, RGB, WHITE, WHITESMOKE};
 use hecs::EntityBuilder;
 use lazy_static::lazy_static;
 use serde::Deserialize;
@@ -6,17 +6,42 @@ use serde_json::from_str;
 use std::{collections::HashMap, fs, sync::Mutex};
 
 mod living_structs;
-use living_structs::LivingData;
+use living_structs::{LivingData, RawLight};
 mod tile_structs;
 use tile_structs::TileData;
+mod item_structs;
 mod perlin_structs;
+use item_structs::ItemData;
+pub use item_structs::RangedData;
+mod breakable_structs;
+use breakable_structs::BreakableData;
+mod recipe_structs;
+pub use recipe_structs::Recipe;
+use recipe_structs::RecipeData;
+mod dialogue_structs;
+use dialogue_structs::DialogueData;
+pub use dialogue_structs::DialogueNode;
 
 use crate::{
-    actor::{CharSprite, Name, Player, Position},
+    actor::{CharSprite, Player, Position},
+    breakable::{Breakable, Drop},
+    burrow::Burrower,
     combat::CombatStats,
+    crafting::Station,
+    dialogue::Dialogue,
+    effects::Effect,
+    energy::{Energy, NORMAL_SPEED},
+    equipment::Gear,
+    experience::XpReward,
+    faction::{Faction, Reaction},
     fov::ViewShed,
+    item::Item,
+    lighting::LightSource,
     map::{TileType, WorldTile},
-    monster::Breed,
+    monster::{Breed, Memory},
+    noise::Hearing,
+    shop::Shop,
+    status::OnHitEffects,
 };
 
 lazy_static! {
@@ -35,11 +60,21 @@ lazy_static! {
 pub struct EntityDatabase {
     living: LivingData,
     tiles: TileData,
+    items: ItemData,
+    breakables: BreakableData,
+    dialogue: DialogueData,
+    recipes: RecipeData,
 
     #[serde(skip)]
     living_index: HashMap<String, usize>,
     #[serde(skip)]
     tile_index: HashMap<String, usize>,
+    #[serde(skip)]
+    item_index: HashMap<String, usize>,
+    #[serde(skip)]
+    breakable_index: HashMap<String, usize>,
+    #[serde(skip)]
+    dialogue_index: HashMap<String, usize>,
 }
 
 impl EntityDatabase {
@@ -47,8 +82,15 @@ impl EntityDatabase {
         Self {
             living: LivingData::default(),
             tiles: TileData::default(),
+            items: ItemData::default(),
+            breakables: BreakableData::default(),
+            dialogue: DialogueData::default(),
+            recipes: RecipeData::default(),
             living_index: HashMap::new(),
             tile_index: HashMap::new(),
+            item_index: HashMap::new(),
+            breakable_index: HashMap::new(),
+            dialogue_index: HashMap::new(),
         }
     }
 
@@ -62,6 +104,18 @@ impl EntityDatabase {
         for (idx, tile) in self.tiles.all.iter().enumerate() {
             self.tile_index.insert(tile.name.clone(), idx);
         }
+
+        for (idx, item) in self.items.all.iter().enumerate() {
+            self.item_index.insert(item.name.clone(), idx);
+        }
+
+        for (idx, breakable) in self.breakables.all.iter().enumerate() {
+            self.breakable_index.insert(breakable.name.clone(), idx);
+        }
+
+        for (idx, dialogue) in self.dialogue.all.iter().enumerate() {
+            self.dialogue_index.insert(dialogue.name.clone(), idx);
+        }
     }
 }
 
@@ -79,9 +133,109 @@ pub fn load_data_for_entities() {
     let tile: TileData = from_str(&contents).expect("Bad JSON in tile.json fix it");
     entity_data.tiles = tile;
 
+    let contents: String =
+        fs::read_to_string("resources/data/item.json").expect("Unable to read to a string, please check file.");
+    let items: ItemData = from_str(&contents).expect("Bad JSON in item.json fix it");
+    entity_data.items = items;
+
+    let contents: String =
+        fs::read_to_string("resources/data/breakable.json").expect("Unable to read to a string, please check file.");
+    let breakables: BreakableData = from_str(&contents).expect("Bad JSON in breakable.json fix it");
+    entity_data.breakables = breakables;
+
+    let contents: String =
+        fs::read_to_string("resources/data/dialogue.json").expect("Unable to read to a string, please check file.");
+    let dialogue: DialogueData = from_str(&contents).expect("Bad JSON in dialogue.json fix it");
+    entity_data.dialogue = dialogue;
+
+    let contents: String =
+        fs::read_to_string("resources/data/recipes.json").expect("Unable to read to a string, please check file.");
+    let recipes: RecipeData = from_str(&contents).expect("Bad JSON in recipes.json fix it");
+    entity_data.recipes = recipes;
+
     ENTITY_DB.lock().unwrap().load(entity_data);
 }
 
+/// Looks up how one faction reacts to another in the reaction table
+pub fn faction_reaction(from: &str, to: &str) -> Reaction {
+    let edb = &ENTITY_DB.lock().unwrap();
+    edb.living
+        .factions
+        .iter()
+        .find(|faction| faction.name == from)
+        .and_then(|faction| faction.reactions.get(to).copied())
+        .unwrap_or(Reaction::Neutral)
+}
+
+/// Finds a node of a conversation, the conversation's first node is given when no id is
+pub fn dialogue_node(dialogue: &str, id: Option<&str>) -> Option<DialogueNode> {
+    let edb = &ENTITY_DB.lock().unwrap();
+    let tree = &edb.dialogue.all[*edb.dialogue_index.get(dialogue)?];
+    let id = id.unwrap_or(&tree.start);
+    tree.nodes.iter().find(|node| node.id == id).cloned()
+}
+
+/// How many coins an item is worth, anything without a value is worth a single coin
+pub fn item_value(name: &str) -> u32 {
+    let edb = &ENTITY_DB.lock().unwrap();
+    match edb.item_index.get(name) {
+        Some(&idx) => edb.items.all[idx].value.unwrap_or(1),
+        None => 1,
+    }
+}
+
+fn light_source(light: &RawLight) -> LightSource {
+    LightSource::new(light.radius, RGB::from_hex(&light.color).unwrap_or(RGB::named(WHITE)))
+}
+
+/// How filling an item is to eat, None if it isn't food
+pub fn item_nutrition(name: &str) -> Option<u32> {
+    let edb = &ENTITY_DB.lock().unwrap();
+    edb.item_index.get(name).and_then(|&idx| edb.items.all[idx].food)
+}
+
+/// How long an item keeps a torch burning, None if it won't burn
+pub fn item_fuel(name: &str) -> Option<u32> {
+    let edb = &ENTITY_DB.lock().unwrap();
+    edb.item_index.get(name).and_then(|&idx| edb.items.all[idx].fuel)
+}
+
+/// How far and hard an item can be thrown or shot, None if it can't be
+pub fn item_ranged(name: &str) -> Option<RangedData> {
+    let edb = &ENTITY_DB.lock().unwrap();
+    edb.item_index
+        .get(name)
+        .and_then(|&idx| edb.items.all[idx].ranged.clone())
+}
+
+/// Whether an item is too heavy to keep hold of while swimming
+pub fn item_heavy(name: &str) -> bool {
+    let edb = &ENTITY_DB.lock().unwrap();
+    edb.item_index
+        .get(name)
+        .and_then(|&idx| edb.items.all[idx].heavy)
+        .unwrap_or(false)
+}
+
+/// What an item does when used, None if it can't be used
+pub fn item_effects(name: &str) -> Option<Vec<Effect>> {
+    let edb = &ENTITY_DB.lock().unwrap();
+    edb.item_index
+        .get(name)
+        .and_then(|&idx| edb.items.all[idx].effects.clone())
+}
+
+/// The bonus an item gives while equipped, None if it can't be equipped
+pub fn item_gear(name: &str) -> Option<Gear> {
+    let edb = &ENTITY_DB.lock().unwrap();
+    edb.item_index.get(name).and_then(|&idx| edb.items.all[idx].gear)
+}
+
+/// Every recipe the player could craft
+pub fn recipes() -> Vec<Recipe> {
+    ENTITY_DB.lock().unwrap().recipes.all.clone()
+}
+
 /// Returns a tile based on a name provided, will return an "empty" tile if the name
 /// provided does not exist.
 pub fn named_tile(name: &str) -> WorldTile {
@@ -126,10 +280,17 @@ pub fn named_tile(name: &str) -> WorldTile {
             "wall" => TileType::Wall,
             "floor" => TileType::Floor,
             "water" => TileType::Water,
+            "lava" => TileType::Lava,
+            "rubble" => TileType::Rubble,
+            "vent" => TileType::Vent,
+            "brush" => TileType::Brush,
             "special" => TileType::Special,
             _ => TileType::Unknown,
         }
     }
+    if let Some(light) = &tile_info.light {
+        builder.light = Some(light_source(light));
+    }
 
     builder
 }
@@ -141,7 +302,7 @@ pub fn named_living_builder(edb: &EntityDatabase, name: &str, pos: Position) ->
     let monster_info = &edb.living.all[edb.living_index[name]];
     let mut eb = EntityBuilder::new();
 
-    eb.add(pos);
+    eb.add(pos.clone());
 
     if let Some(sprite) = &monster_info.sprite {
         let fg = RGB::from_hex(&sprite.fg).unwrap_or(RGB::named(PURPLE));
@@ -152,17 +313,18 @@ pub fn named_living_builder(edb: &EntityDatabase, name: &str, pos: Position) ->
 
     if let Some(breed) = &monster_info.breed {
         if let Some(ai) = &monster_info.ai {
-            eb.add(Breed::from(name, breed, ai));
+            eb.add(Breed::from(name, breed, ai, pos.0));
         } else {
-            eb.add(Breed::from(name, breed, "basic"));
+            eb.add(Breed::from(name, breed, "basic", pos.0));
         }
+        eb.add(Memory::new());
     }
 
     if let Some(view_distance) = &monster_info.view_range {
         eb.add(ViewShed::new(*view_distance));
     }
 
-    if let Some(_) = &monster_info.player {
+    if monster_info.player.is_some() {
         eb.add(Player);
     }
 
@@ -170,8 +332,99 @@ pub fn named_living_builder(edb: &EntityDatabase, name: &str, pos: Position) ->
         eb.add(CombatStats::new(stats.hp, stats.str, stats.def));
     }
 
-    if name == "Player" {
-        eb.add(Name(name.to_string()));
+    eb.add(Energy::new(monster_info.speed.unwrap_or(NORMAL_SPEED)));
+
+    if let Some(xp) = &monster_info.xp {
+        eb.add(XpReward(*xp));
+    }
+
+    if let Some(true) = monster_info.burrows {
+        eb.add(Burrower);
+    }
+
+    if let Some(hearing) = &monster_info.hearing {
+        eb.add(Hearing(*hearing));
+    }
+
+    if let Some(faction) = &monster_info.faction {
+        eb.add(Faction(faction.clone()));
+    }
+
+    if let Some(dialogue) = &monster_info.dialogue {
+        eb.add(Dialogue(dialogue.clone()));
+    }
+
+    if let Some(stock) = &monster_info.shop {
+        eb.add(Shop { stock: stock.clone() });
+    }
+
+    if let Some(light) = &monster_info.light {
+        eb.add(light_source(light));
+    }
+
+    if let Some(on_hit) = &monster_info.on_hit {
+        eb.add(OnHitEffects(on_hit.clone()));
+    }
+
+    if let Some(affinity) = monster_info.water {
+        eb.add(affinity);
+    }
+
+    Some(eb)
+}
+
+/// Builds an item lying on the ground at a position
+pub fn named_item_builder(edb: &EntityDatabase, name: &str, pos: Position) -> Option<EntityBuilder> {
+    if !edb.item_index.contains_key(name) {
+        return None;
+    }
+    let item_info = &edb.items.all[edb.item_index[name]];
+    let mut eb = EntityBuilder::new();
+
+    eb.add(pos);
+    eb.add(Item::new(name));
+
+    if let Some(sprite) = &item_info.sprite {
+        let fg = RGB::from_hex(&sprite.fg).unwrap_or(RGB::named(PURPLE));
+        let bg = RGB::from_hex(&sprite.bg).unwrap_or(RGB::named(WHITESMOKE));
+
+        eb.add(CharSprite::rgb(sprite.glyph, fg, bg));
+    }
+
+    Some(eb)
+}
+
+/// Builds a breakable object such as a crate or crystal at a position
+pub fn named_breakable_builder(edb: &EntityDatabase, name: &str, pos: Position) -> Option<EntityBuilder> {
+    if !edb.breakable_index.contains_key(name) {
+        return None;
+    }
+    let breakable_info = &edb.breakables.all[edb.breakable_index[name]];
+    let mut eb = EntityBuilder::new();
+
+    eb.add(pos);
+
+    if let Some(sprite) = &breakable_info.sprite {
+        let fg = RGB::from_hex(&sprite.fg).unwrap_or(RGB::named(PURPLE));
+        let bg = RGB::from_hex(&sprite.bg).unwrap_or(RGB::named(WHITESMOKE));
+
+        eb.add(CharSprite::rgb(sprite.glyph, fg, bg));
+    }
+
+    let drops = match &breakable_info.drops {
+        Some(drops) => drops
+            .iter()
+            .map(|drop| Drop {
+                item: drop.item.clone(),
+                chance: drop.chance,
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    eb.add(Breakable::new(name, breakable_info.hp, drops));
+
+    if let Some(true) = breakable_info.station {
+        eb.add(Station(name.to_string()));
     }
 
     Some(eb)
@@ -0,0 +1,27 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Default)]
+pub struct RecipeData {
+    pub all: Vec<Recipe>,
+}
+
+/// How to make an item out of others
+#[derive(Deserialize, Debug, Clone)]
+pub struct Recipe {
+    pub name: String, // the item made
+    #[serde(default = "one")]
+    pub count: usize, // how many are made at once
+    pub ingredients: Vec<Ingredient>,
+    pub station: Option<String>, // what the player needs to be next to, anywhere will do when there is none
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Ingredient {
+    pub item: String,
+    #[serde(default = "one")]
+    pub count: usize,
+}
+
+fn one() -> usize {
+    1
+}
@@ -1,6 +1,6 @@
 use serde::Deserialize;
 
-use super::living_structs::RawSprite;
+use super::living_structs::{RawLight, RawSprite};
 
 #[derive(Deserialize, Debug, Default)]
 pub struct TileData {
@@ -15,6 +15,7 @@ pub struct Tile {
     pub sprite: Option<RawSprite>,
     // pub destructible_info: Option<DestructibleInfo>,
     pub tile_type: Option<String>,
+    pub light: Option<RawLight>,
 }
 
 // #[derive(Deserialize, Debug)]
@@ -0,0 +1,91 @@
+/*  Dialogue lets the player talk with the friendly folk of the caves.
+    Conversations are trees loaded from dialogue.json, each reply the player picks leads to another node
+    until one ends the conversation.
+*/
+use bracket_terminal::prelude::*;
+use hecs::{Entity, World};
+
+use crate::{
+    data_read::{dialogue_node, DialogueNode},
+    monster::Breed,
+    state::{RunState, State},
+};
+
+/// Which conversation a being has with the player when they talk
+pub struct Dialogue(pub String);
+
+/// Where the player is in a conversation
+#[derive(Clone)]
+pub struct Conversation {
+    pub speaker: String,
+    pub dialogue: String,
+    pub node: String,
+}
+
+/// Begins talking with a being, None if it has nothing to say
+pub fn start_conversation(world: &World, with: Entity) -> Option<Conversation> {
+    let mut query = world.query_one::<(&Dialogue, &Breed)>(with).ok()?;
+    let (dialogue, breed) = query.get()?;
+    let start = dialogue_node(&dialogue.0, None)?;
+    Some(Conversation {
+        speaker: breed.name.clone(),
+        dialogue: dialogue.0.clone(),
+        node: start.id,
+    })
+}
+
+/// Shows the current line of a conversation and moves along it as the player picks replies
+pub fn run_dialogue_systems(state: &mut State, ctx: &mut BTerm, conversation: Conversation) -> RunState {
+    let node = match dialogue_node(&conversation.dialogue, Some(&conversation.node)) {
+        Some(node) => node,
+        None => return RunState::InGame,
+    };
+
+    if let Some(key) = ctx.key {
+        if key == VirtualKeyCode::Escape {
+            return RunState::InGame;
+        }
+        if let Some(option) = option_index(key).and_then(|idx| node.options.get(idx)) {
+            return match &option.next {
+                Some(next) => RunState::Dialogue(Conversation {
+                    node: next.clone(),
+                    ..conversation
+                }),
+                None => RunState::InGame,
+            };
+        }
+    }
+
+    draw_dialogue_box(ctx, state, &conversation.speaker, &node);
+    RunState::Dialogue(conversation)
+}
+
+fn option_index(key: VirtualKeyCode) -> Option<usize> {
+    match key {
+        VirtualKeyCode::Key1 => Some(0),
+        VirtualKeyCode::Key2 => Some(1),
+        VirtualKeyCode::Key3 => Some(2),
+        VirtualKeyCode::Key4 => Some(3),
+        VirtualKeyCode::Key5 => Some(4),
+        _ => None,
+    }
+}
+
+/// Covers the message log with what is being said and the replies to choose from
+fn draw_dialogue_box(ctx: &mut BTerm, state: &State, speaker: &str, node: &DialogueNode) {
+    let top = state.config.screensize_y - 10;
+    ctx.draw_box(0, top, 99, 9, WHITE, BLACK);
+    ctx.print_color(2, top, YELLOW, BLACK, format!(" {speaker} "));
+    ctx.print(1, top + 1, &node.text);
+
+    for (idx, option) in node.options.iter().enumerate() {
+        ctx.print_color(
+            2,
+            top + 3 + idx,
+            LIGHT_BLUE,
+            BLACK,
+            format!("{}. {}", idx + 1, option.text),
+        );
+    }
+    ctx.print_color(2, top + 9, GREY, BLACK, " Esc to leave ");
+}
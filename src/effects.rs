@@ -0,0 +1,222 @@
+/*  Effects are what happens when an item is used, drunk, read or thrown.
+    Items describe their effects in their data and they are all applied here at some point on the map,
+    whether that is where the player stands or where a thrown bomb lands.
+*/
+use bracket_pathfinding::prelude::{DistanceAlg, Point};
+use hecs::{Entity, With};
+use serde::Deserialize;
+
+use crate::{
+    actor::{Player, Position},
+    breakable::{smash, Breakable},
+    combat::CombatStats,
+    data_read::named_tile,
+    experience::credit_player_kill,
+    fov::ViewShed,
+    map::{Destructible, Map},
+    messagelog::Message,
+    monster::Breed,
+    morgue::player_is_dead,
+    noise::{Noise, BLAST_NOISE},
+    status::{StatusEffects, StatusKind},
+    structure::cave_in,
+    State,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(tag = "effect", rename_all = "snake_case")]
+pub enum Effect {
+    Heal { amount: u32 },               // restores health to whoever is at the point
+    Cure { status: StatusKind },        // rids whoever is at the point of a status
+    Blast { radius: u32, damage: u32 }, // blows apart rock and hurts every being nearby
+    Reveal { radius: u32 },             // lights up the area long enough for the player to take it in
+    MapFloor,                           // shows the layout of the whole floor
+}
+
+/// Applies each effect in turn centered on a point
+pub fn apply_effects(state: &mut State, effects: &[Effect], at: Point) {
+    for effect in effects {
+        match *effect {
+            Effect::Heal { amount } => heal(state, at, amount),
+            Effect::Cure { status } => cure(state, at, status),
+            Effect::Blast { radius, damage } => blast(state, at, radius, damage),
+            Effect::Reveal { radius } => {
+                for idx in area(&state.map, at, radius) {
+                    state.map.discovered[idx] = true;
+                }
+                state.message_log.push(Message::new(
+                    "The area is lit up in a harsh glare".to_string(),
+                    state.turn_counter,
+                ));
+            }
+            Effect::MapFloor => {
+                for idx in walls_and_openings(&state.map) {
+                    state.map.discovered[idx] = true;
+                }
+                state.message_log.push(Message::new(
+                    "The layout of the floor fills your mind".to_string(),
+                    state.turn_counter,
+                ));
+            }
+        }
+    }
+}
+
+/// Every tile on the map within a radius of a point
+pub fn area(map: &Map, center: Point, radius: u32) -> Vec<usize> {
+    let radius = radius as i32;
+    let mut tiles = Vec::new();
+    for x in (center.x - radius)..=(center.x + radius) {
+        for y in (center.y - radius)..=(center.y + radius) {
+            let pt = Point::new(x, y);
+            if map.within_bounds(pt) && DistanceAlg::Pythagoras.distance2d(center, pt) <= radius as f32 + 0.5 {
+                tiles.push(pt.to_index(map.width));
+            }
+        }
+    }
+    tiles
+}
+
+/// Every open tile and the walls bordering them, solid rock deep inside the walls is left out
+fn walls_and_openings(map: &Map) -> Vec<usize> {
+    (0..map.tiles.len())
+        .filter(|&idx| {
+            let pt = map.idx_to_point(idx);
+            area(map, pt, 1).iter().any(|&near| !map.tiles[near].is_blocking)
+        })
+        .collect()
+}
+
+/// The beings standing within a radius of a point
+fn beings_within(state: &State, center: Point, radius: u32) -> Vec<(Entity, String)> {
+    state
+        .world
+        .query::<(&Position, &CombatStats, Option<&Breed>)>()
+        .iter()
+        .filter(|(_, (pos, _, _))| DistanceAlg::Pythagoras.distance2d(center, pos.0) <= radius as f32 + 0.5)
+        .map(|(e, (_, _, breed))| match breed {
+            Some(breed) => (e, breed.name.clone()),
+            None => (e, "Player".to_string()),
+        })
+        .collect()
+}
+
+fn heal(state: &mut State, at: Point, amount: u32) {
+    for (being, name) in beings_within(state, at, 0) {
+        if let Ok(mut stats) = state.world.get::<&mut CombatStats>(being) {
+            stats.health = (stats.health + amount).min(stats.max_health);
+            state
+                .message_log
+                .push(Message::new(format!("{name} healed {amount} hp"), state.turn_counter));
+        }
+    }
+}
+
+fn cure(state: &mut State, at: Point, status: StatusKind) {
+    for (being, _) in beings_within(state, at, 0) {
+        let cured = match state.world.get::<&mut StatusEffects>(being) {
+            Ok(mut effects) => {
+                let before = effects.0.len();
+                effects.0.retain(|effect| effect.kind != status);
+                effects.0.len() < before
+            }
+            Err(..) => false,
+        };
+        if cured && state.world.get::<&Player>(being).is_ok() {
+            state.message_log.push(Message::new(
+                format!("You are no longer {}", status.describe()),
+                state.turn_counter,
+            ));
+        }
+    }
+}
+
+/// Breaks every destructible within the radius and hurts the beings caught in it, the rock around may give way
+fn blast(state: &mut State, at: Point, radius: u32, damage: u32) {
+    let turn = state.turn_counter;
+    state
+        .message_log
+        .push(Message::new("A deafening blast shakes the cave!".to_string(), turn));
+
+    for idx in area(&state.map, at, radius) {
+        match state.map.destructibles[idx] {
+            Some(Destructible::Tile { .. }) => {
+                state.map.tiles[idx] = named_tile("Grass Floor");
+                state.map.destructibles[idx] = None;
+                state.map.excavated[idx] = true;
+                state.map.air_dirty = true;
+            }
+            Some(Destructible::Entity(e)) => {
+                // a blast leaves nothing standing, it is hit until it falls apart
+                let hits = state.world.get::<&Breakable>(e).map(|b| b.hp).unwrap_or(0);
+                for _ in 0..hits {
+                    smash(&mut state.world, &mut state.map, e);
+                }
+            }
+            None => {}
+        }
+    }
+
+    for (being, name) in beings_within(state, at, radius) {
+        let slain = match state.world.get::<&mut CombatStats>(being) {
+            Ok(mut stats) if stats.health > 0 => {
+                stats.health = stats.health.saturating_sub(damage);
+                state
+                    .message_log
+                    .push(Message::new(format!("{name} took {damage} hp from the blast"), turn));
+                stats.health == 0
+            }
+            _ => false,
+        };
+        if slain && state.world.get::<&Player>(being).is_err() {
+            credit_player_kill(state, being);
+        }
+    }
+
+    cave_in(&mut state.map, &mut state.world, at, &mut state.message_log, turn);
+    state.noises.push(Noise::new(at, BLAST_NOISE));
+    if let Some((_, view)) = state
+        .world
+        .query_mut::<With<&mut ViewShed, &Player>>()
+        .into_iter()
+        .next()
+    {
+        view.dirty = true;
+    }
+    if player_is_dead(state) && state.record.killer.is_none() {
+        state.record.killer = Some("a blast".to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::TileType;
+
+    #[test]
+    fn effects_are_read_from_data() {
+        let effects: Vec<Effect> = serde_json::from_str(
+            r#"[{ "effect": "heal", "amount": 20 }, { "effect": "cure", "status": "poisoned" }, { "effect": "map_floor" }]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            effects,
+            vec![
+                Effect::Heal { amount: 20 },
+                Effect::Cure {
+                    status: StatusKind::Poisoned
+                },
+                Effect::MapFloor
+            ]
+        );
+    }
+
+    #[test]
+    fn area_is_round_and_clipped_to_the_map() {
+        let map = Map::test_strip(10, 10, &[TileType::Floor; 100]);
+        assert_eq!(area(&map, Point::new(5, 5), 0), vec![55]);
+        assert_eq!(area(&map, Point::new(5, 5), 1).len(), 9);
+        assert_eq!(area(&map, Point::new(0, 0), 1).len(), 4);
+        assert!(!area(&map, Point::new(5, 5), 3).contains(&Point::new(2, 2).to_index(10)));
+    }
+}
@@ -0,0 +1,105 @@
+/*  Energy drives who gets to act and how often.
+    Every action the player takes costs time, all other beings earn energy for that time based on their speed
+    and get to act once for every ACTION_THRESHOLD of energy they have stored up.
+*/
+use hecs::{With, World};
+
+use crate::{
+    actor::Player,
+    hunger::player_hunger,
+    status::{StatusEffects, StatusKind},
+};
+
+/// Energy needed before a being can take an action
+pub const ACTION_THRESHOLD: i32 = 100;
+/// Speed that acts exactly once per player step, the player's usual speed
+pub const NORMAL_SPEED: u32 = 100;
+
+// Time costs of actions, a normal speed being takes one action per 100
+pub const STEP_COST: u32 = 100;
+pub const SWIM_COST: u32 = 200;
+pub const ATTACK_COST: u32 = 100;
+pub const WAIT_COST: u32 = 100;
+pub const PICKUP_COST: u32 = 50;
+pub const EAT_COST: u32 = 100;
+pub const FORAGE_COST: u32 = 100;
+pub const REFUEL_COST: u32 = 100;
+pub const USE_COST: u32 = 100;
+pub const CRAFT_COST: u32 = 200;
+pub const MINE_COST: u32 = 200;
+pub const DIG_COST: u32 = 300;
+
+pub struct Energy {
+    pub speed: u32,
+    pub energy: i32,
+}
+
+impl Energy {
+    pub fn new(speed: u32) -> Self {
+        Self { speed, energy: 0 }
+    }
+
+    /// Stores up energy for time passing
+    pub fn gain(&mut self, time: u32) {
+        self.energy += (time * self.speed / NORMAL_SPEED) as i32;
+    }
+
+    pub fn ready(&self) -> bool {
+        self.energy >= ACTION_THRESHOLD
+    }
+
+    pub fn spend(&mut self, cost: u32) {
+        self.energy -= cost as i32;
+    }
+}
+
+/// How much time passes in the world for the player taking an action of some cost
+/// A faster player gets more done before anything else can act, a hungry or slowed one less
+pub fn player_action_time(world: &World, cost: u32) -> u32 {
+    let cost = match player_hunger(world) {
+        Some(hunger) => hunger.action_cost(cost),
+        None => cost,
+    };
+    let cost = match world.query::<With<&StatusEffects, &Player>>().iter().next() {
+        Some((_, effects)) => slowed_cost(effects, cost),
+        None => cost,
+    };
+    match world.query::<With<&Energy, &Player>>().iter().next() {
+        Some((_, energy)) if energy.speed > 0 => cost * NORMAL_SPEED / energy.speed,
+        _ => cost,
+    }
+}
+
+/// Slowed beings take twice as long to do anything
+pub fn slowed_cost(effects: &StatusEffects, cost: u32) -> u32 {
+    match effects.has(StatusKind::Slowed) {
+        true => cost * 2,
+        false => cost,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts how many actions a being of some speed gets while the player takes a number of steps
+    fn actions_over(speed: u32, player_steps: u32) -> u32 {
+        let mut energy = Energy::new(speed);
+        let mut actions = 0;
+        for _ in 0..player_steps {
+            energy.gain(STEP_COST);
+            while energy.ready() {
+                energy.spend(STEP_COST);
+                actions += 1;
+            }
+        }
+        actions
+    }
+
+    #[test]
+    fn speed_scales_actions() {
+        assert_eq!(actions_over(200, 10), 20);
+        assert_eq!(actions_over(100, 10), 10);
+        assert_eq!(actions_over(50, 10), 5);
+    }
+}
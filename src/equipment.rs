@@ -0,0 +1,127 @@
+/*  Equipment is the gear the player wields or wears for a lasting bonus, like a pickaxe or a suit of mail.
+    There is one slot for the hand and one for the body, putting something new in a slot puts the old gear back
+    in the player's pack.
+*/
+use hecs::{With, World};
+use serde::Deserialize;
+
+use crate::{
+    actor::Player,
+    combat::CombatStats,
+    data_read::item_gear,
+    item::{Inventory, Item},
+    messagelog::Message,
+    State,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EquipSlot {
+    Hand, // tools and weapons
+    Body, // armor
+}
+
+/// The bonus an item gives while it is equipped
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Gear {
+    pub slot: EquipSlot,
+    #[serde(default)]
+    pub strength: i32,
+    #[serde(default)]
+    pub defense: i32,
+    #[serde(default)]
+    pub mining: usize, // extra damage dealt to rock with each swing
+}
+
+/// What the player has in each slot
+#[derive(Default)]
+pub struct Equipment {
+    pub hand: Option<Item>,
+    pub body: Option<Item>,
+}
+
+impl Equipment {
+    fn slot_mut(&mut self, slot: EquipSlot) -> &mut Option<Item> {
+        match slot {
+            EquipSlot::Hand => &mut self.hand,
+            EquipSlot::Body => &mut self.body,
+        }
+    }
+}
+
+/// Adds or takes away the bonus of a piece of gear from the wearer's stats
+fn apply_bonus(stats: &mut CombatStats, gear: &Gear, sign: i32) {
+    stats.strength += gear.strength * sign;
+    stats.defense += gear.defense * sign;
+}
+
+/// Equips an item from the player's pack, whatever was in its slot goes back into the pack
+/// Returns true if it was equipped
+pub fn equip(state: &mut State, name: &str) -> bool {
+    let Some(slot) = equip_from_pack(&mut state.world, name) else {
+        return false;
+    };
+    let verb = match slot {
+        EquipSlot::Hand => "wield",
+        EquipSlot::Body => "put on",
+    };
+    state
+        .message_log
+        .push(Message::new(format!("You {verb} the {name}"), state.turn_counter));
+    true
+}
+
+/// Moves a piece of gear from the player's pack into its slot and applies its bonus
+/// Returns the slot it went into, if it could be equipped
+pub fn equip_from_pack(world: &mut World, name: &str) -> Option<EquipSlot> {
+    let gear = item_gear(name)?;
+    let (_, (equipment, inventory, stats)) = world
+        .query_mut::<With<(&mut Equipment, &mut Inventory, &mut CombatStats), &Player>>()
+        .into_iter()
+        .next()?;
+    let idx = inventory.items.iter().position(|item| item.name == name)?;
+
+    let item = inventory.items.remove(idx);
+    if let Some(old) = equipment.slot_mut(gear.slot).replace(item) {
+        if let Some(old_gear) = item_gear(&old.name) {
+            apply_bonus(stats, &old_gear, -1);
+        }
+        inventory.items.push(old);
+    }
+    apply_bonus(stats, &gear, 1);
+    Some(gear.slot)
+}
+
+/// The wearer's stats with the bonuses of everything they have equipped taken back off
+pub fn stats_without_gear(equipment: &Equipment, stats: &CombatStats) -> CombatStats {
+    let mut bare = *stats;
+    for item in equipment.hand.iter().chain(equipment.body.iter()) {
+        if let Some(gear) = item_gear(&item.name) {
+            apply_bonus(&mut bare, &gear, -1);
+        }
+    }
+    bare
+}
+
+/// Takes off every piece of gear that matches and its bonus with it, returns the gear that was taken off
+pub fn strip_gear(equipment: &mut Equipment, stats: &mut CombatStats, strip: impl Fn(&Item) -> bool) -> Vec<Item> {
+    let mut stripped = Vec::new();
+    for slot in [EquipSlot::Hand, EquipSlot::Body] {
+        if let Some(item) = equipment.slot_mut(slot).take_if(|item| strip(item)) {
+            if let Some(gear) = item_gear(&item.name) {
+                apply_bonus(stats, &gear, -1);
+            }
+            stripped.push(item);
+        }
+    }
+    stripped
+}
+
+/// How hard the player strikes rock, bare handed they chip away one hit at a time
+pub fn mining_power(world: &World) -> usize {
+    let tool = match world.query::<With<&Equipment, &Player>>().iter().next() {
+        Some((_, equipment)) => equipment.hand.as_ref().and_then(|item| item_gear(&item.name)),
+        None => None,
+    };
+    1 + tool.map_or(0, |gear| gear.mining)
+}
@@ -0,0 +1,119 @@
+use hecs::{Entity, With, World};
+use serde::{Deserialize, Serialize};
+
+use crate::{actor::Player, combat::CombatStats, messagelog::Message, state::State};
+
+/// How much experience a being is worth when slain
+pub struct XpReward(pub u32);
+
+/// Tracks the player's progression, xp is the total earned over the run
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Experience {
+    pub level: u32,
+    pub xp: u32,
+}
+
+impl Experience {
+    pub fn new() -> Self {
+        Self { level: 1, xp: 0 }
+    }
+
+    /// Total experience needed to advance past the current level
+    pub fn next_threshold(&self) -> u32 {
+        xp_threshold(self.level)
+    }
+
+    /// Adds experience and returns how many levels were gained from it
+    pub fn gain(&mut self, amount: u32) -> u32 {
+        self.xp += amount;
+        let mut levels_gained = 0;
+        while self.xp >= self.next_threshold() {
+            self.level += 1;
+            levels_gained += 1;
+        }
+        levels_gained
+    }
+}
+
+impl Default for Experience {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Total experience needed to go from `level` to the next, each level costs 20 more than the last
+pub fn xp_threshold(level: u32) -> u32 {
+    10 * level * (level + 1)
+}
+
+/// Stat increases for reaching a new level, defense only goes up every other level
+pub fn level_up_stats(stats: &mut CombatStats, new_level: u32) {
+    stats.max_health += 10;
+    stats.health += 10;
+    stats.strength += 1;
+    if new_level.is_multiple_of(2) {
+        stats.defense += 1;
+    }
+}
+
+/// Gives the player the experience for slaying a being and levels them up if they earned enough
+pub fn gain_kill_xp(world: &mut World, message_log: &mut Vec<Message>, slain: Entity, turn_sent: usize) {
+    let reward = match world.get::<&XpReward>(slain) {
+        Ok(reward) => reward.0,
+        Err(..) => return,
+    };
+
+    if let Some((_, (exp, stats))) = world
+        .query_mut::<With<(&mut Experience, &mut CombatStats), &Player>>()
+        .into_iter()
+        .next()
+    {
+        message_log.push(Message::new(format!("Player gained {reward} xp"), turn_sent));
+        let levels_gained = exp.gain(reward);
+        for new_level in (exp.level - levels_gained + 1)..=exp.level {
+            level_up_stats(stats, new_level);
+            message_log.push(Message::new(
+                format!("Welcome to level {new_level}! You feel stronger"),
+                turn_sent,
+            ));
+        }
+    }
+}
+
+/// Counts a being the player slew towards the run's record and hands out its experience
+pub fn credit_player_kill(state: &mut State, slain: Entity) {
+    state.record.kills += 1;
+    gain_kill_xp(&mut state.world, &mut state.message_log, slain, state.turn_counter);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thresholds_grow_each_level() {
+        assert_eq!(xp_threshold(1), 20);
+        assert_eq!(xp_threshold(2), 60);
+        assert_eq!(xp_threshold(3), 120);
+    }
+
+    #[test]
+    fn gaining_enough_xp_skips_levels() {
+        let mut exp = Experience::new();
+        assert_eq!(exp.gain(19), 0);
+        assert_eq!(exp.gain(50), 2);
+        assert_eq!(exp.level, 3);
+        assert_eq!(exp.xp, 69);
+    }
+
+    #[test]
+    fn level_up_heals_and_strengthens() {
+        let mut stats = CombatStats::new(20, 2, 1);
+        stats.health = 5;
+        level_up_stats(&mut stats, 2);
+        assert_eq!(stats.max_health, 30);
+        assert_eq!(stats.health, 15);
+        assert_eq!(stats.strength, 3);
+        assert_eq!(stats.defense, 2);
+    }
+}
@@ -0,0 +1,109 @@
+/*  Factions decide who fights who.
+    Every being belongs to one and the reaction table in living.json says how a faction treats the others.
+*/
+use bracket_pathfinding::prelude::{DistanceAlg, Point};
+use hecs::{Entity, With, World};
+use serde::Deserialize;
+
+use crate::{
+    actor::{Player, Position},
+    combat::CombatStats,
+    data_read::faction_reaction,
+    fov::ViewShed,
+    monster::Breed,
+    State,
+};
+
+#[derive(Clone, Debug)]
+pub struct Faction(pub String);
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Reaction {
+    Hostile,
+    Neutral,
+    Friendly,
+}
+
+/// How a member of one faction treats a member of another, beings always get along with their own
+pub fn reaction_between(from: &Faction, to: &Faction) -> Reaction {
+    if from.0 == to.0 {
+        return Reaction::Friendly;
+    }
+    faction_reaction(&from.0, &to.0)
+}
+
+/// A being picked out to be hunted
+#[derive(Clone, Copy, Debug)]
+pub struct Target {
+    pub entity: Entity,
+    pub pos: Point,
+    pub is_player: bool,
+}
+
+/// The closest living being that `me` can see and is hostile towards
+pub fn nearest_hostile(world: &World, me: Entity) -> Option<Target> {
+    let (my_pos, my_faction, visible) = {
+        let mut query = world.query_one::<(&Position, &Faction, &ViewShed)>(me).ok()?;
+        let (pos, faction, view) = query.get()?;
+        (pos.0, faction.clone(), view.visible_tiles.clone())
+    };
+
+    let mut nearest: Option<(f32, Target)> = None;
+    for (entity, (pos, faction, stats, player)) in world
+        .query::<(&Position, &Faction, &CombatStats, Option<&Player>)>()
+        .iter()
+    {
+        if entity == me || stats.health == 0 || !visible.contains(&pos.0) {
+            continue;
+        }
+        let dist = DistanceAlg::Pythagoras.distance2d(my_pos, pos.0);
+        if nearest.as_ref().is_some_and(|(best, _)| *best <= dist) {
+            continue;
+        }
+        if reaction_between(&my_faction, faction) == Reaction::Hostile {
+            let target = Target {
+                entity,
+                pos: pos.0,
+                is_player: player.is_some(),
+            };
+            nearest = Some((dist, target));
+        }
+    }
+    nearest.map(|(_, target)| target)
+}
+
+/// Every being hostile to the player that the player can see along with its name, nearest first
+/// Hidden ambushers aren't seen until they spring out
+pub fn visible_hostiles(state: &State) -> Vec<(Entity, Point, String)> {
+    let (player_pos, player_faction) = match state
+        .world
+        .query::<With<(&Position, &Faction), &Player>>()
+        .iter()
+        .next()
+    {
+        Some((_, (pos, faction))) => (pos.0, faction.clone()),
+        None => return Vec::new(),
+    };
+
+    let mut hostiles = Vec::new();
+    for (entity, (pos, faction, breed, stats)) in state
+        .world
+        .query::<(&Position, &Faction, &Breed, &CombatStats)>()
+        .iter()
+    {
+        let idx = pos.0.to_index(state.map.width);
+        if stats.health == 0 || breed.is_concealed() || !state.visible.get(idx).copied().unwrap_or(false) {
+            continue;
+        }
+        if reaction_between(faction, &player_faction) == Reaction::Hostile {
+            hostiles.push((entity, pos.0, breed.name.clone()));
+        }
+    }
+    hostiles.sort_by(|(_, a, _), (_, b, _)| {
+        let dist_a = DistanceAlg::Pythagoras.distance2d(player_pos, *a);
+        let dist_b = DistanceAlg::Pythagoras.distance2d(player_pos, *b);
+        dist_a.total_cmp(&dist_b)
+    });
+    hostiles
+}
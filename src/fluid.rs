@@ -0,0 +1,315 @@
+/*  Fluids give water and lava a life of their own. Every tile holds some amount of at most one fluid which
+    flows into open neighbors holding less, until it settles out level. Aquifers are pockets of rock soaked
+    through with water, or lava down deep, that pour out into the tunnels once they are mined into.
+*/
+use bracket_pathfinding::prelude::Point;
+use hecs::Entity;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actor::{Player, Position},
+    combat::CombatStats,
+    data_read::named_tile,
+    map::{Map, TileType},
+    messagelog::Message,
+    monster::Breed,
+    morgue::player_is_dead,
+    State,
+};
+
+/// Most fluid a single tile can hold, aquifers are filled to the brim
+pub const MAX_LEVEL: u8 = 8;
+/// Lava is thick and only creeps along every few turns
+const LAVA_FLOW_INTERVAL: usize = 3;
+/// Health lost each turn spent in lava
+const LAVA_DAMAGE: u32 = 8;
+/// Chance each turn of lava setting alight brush right next to it
+const BURN_CHANCE: f32 = 0.3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum FluidKind {
+    Water,
+    Lava,
+}
+
+/// Some amount of a fluid sitting in a tile
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Fluid {
+    pub kind: FluidKind,
+    pub level: u8,
+}
+
+impl FluidKind {
+    /// The tile this fluid turns whatever it floods into
+    fn tile(&self) -> &'static str {
+        match self {
+            FluidKind::Water => "Deep Water",
+            FluidKind::Lava => "Lava",
+        }
+    }
+
+    fn tile_type(&self) -> TileType {
+        match self {
+            FluidKind::Water => TileType::Water,
+            FluidKind::Lava => TileType::Lava,
+        }
+    }
+
+    /// The rock of an aquifer holding this fluid
+    pub fn rock(&self) -> &'static str {
+        match self {
+            FluidKind::Water => "Damp Rock",
+            FluidKind::Lava => "Hot Rock",
+        }
+    }
+}
+
+/// Fluid for every water and lava tile already on the map, a single level each so they stay put until disturbed
+pub fn settled_fluids(map: &Map) -> Vec<Option<Fluid>> {
+    map.tiles
+        .iter()
+        .map(|tile| match tile.tile_type {
+            TileType::Water => Some(Fluid {
+                kind: FluidKind::Water,
+                level: 1,
+            }),
+            TileType::Lava => Some(Fluid {
+                kind: FluidKind::Lava,
+                level: 1,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether fluid can flow into a tile, stairs, vents and anything solid hold it back
+fn floodable(map: &Map, idx: usize) -> bool {
+    map.destructibles[idx].is_none()
+        && matches!(
+            map.tiles[idx].tile_type,
+            TileType::Floor | TileType::Brush | TileType::Water | TileType::Lava
+        )
+}
+
+fn cardinal_neighbors(map: &Map, idx: usize) -> Vec<usize> {
+    let pt = map.idx_to_point(idx);
+    [Point::new(-1, 0), Point::new(1, 0), Point::new(0, -1), Point::new(0, 1)]
+        .into_iter()
+        .map(|delta| pt + delta)
+        .filter(|next| map.within_bounds(*next))
+        .map(|next| next.to_index(map.width))
+        .collect()
+}
+
+/// Works out where the fluids flow this turn, every tile passes one unit to each neighbor at least two lower
+/// Returns the new fluids and the tiles where water and lava met and the lava cooled into stone
+pub fn spread(map: &Map, lava_flows: bool) -> (Vec<Option<Fluid>>, Vec<usize>) {
+    let mut next = map.fluids.clone();
+    let mut cooled = Vec::new();
+
+    for idx in 0..map.fluids.len() {
+        let Some(Fluid { kind, .. }) = map.fluids[idx] else {
+            continue;
+        };
+        if kind == FluidKind::Lava && !lava_flows {
+            continue;
+        }
+        for neighbor in cardinal_neighbors(map, idx) {
+            let Some(source) = next[idx] else {
+                break;
+            };
+            if source.level < 2 {
+                break;
+            }
+            if !floodable(map, neighbor) {
+                continue;
+            }
+            let flowed = match next[neighbor] {
+                Some(other) if other.kind != kind => {
+                    next[neighbor] = None;
+                    cooled.push(neighbor);
+                    true
+                }
+                Some(other) if other.level + 1 < source.level => {
+                    next[neighbor] = Some(Fluid {
+                        level: other.level + 1,
+                        ..other
+                    });
+                    true
+                }
+                Some(_) => false,
+                None => {
+                    next[neighbor] = Some(Fluid { kind, level: 1 });
+                    true
+                }
+            };
+            if flowed {
+                next[idx] = Some(Fluid {
+                    level: source.level - 1,
+                    ..source
+                });
+            }
+        }
+    }
+
+    (next, cooled)
+}
+
+/// Lets water and lava flow for a turn, flooding the tiles they reach
+/// Lava sets fire to brush and burns anything standing in it
+pub fn flow_fluids(state: &mut State) {
+    let map = &mut state.map;
+    // maps saved before fluids were tracked start out settled
+    if map.fluids.len() != map.tiles.len() {
+        map.fluids = settled_fluids(map);
+    }
+
+    let (fluids, cooled) = spread(map, state.turn_counter.is_multiple_of(LAVA_FLOW_INTERVAL));
+    map.fluids = fluids;
+    for idx in cooled {
+        map.tiles[idx] = named_tile("Stone Floor");
+        map.air_dirty = true;
+    }
+    for idx in 0..map.tiles.len() {
+        if let Some(fluid) = map.fluids[idx] {
+            if floodable(map, idx) && map.tiles[idx].tile_type != fluid.kind.tile_type() {
+                map.tiles[idx] = named_tile(fluid.kind.tile());
+                map.air_dirty = true;
+            }
+        }
+    }
+
+    burn_brush(map);
+    scorch_beings(state);
+}
+
+/// Brush next to lava now and then catches fire and burns away
+fn burn_brush(map: &mut Map) {
+    let mut rng = rand::thread_rng();
+    for idx in 0..map.tiles.len() {
+        if !matches!(
+            map.fluids[idx],
+            Some(Fluid {
+                kind: FluidKind::Lava,
+                ..
+            })
+        ) {
+            continue;
+        }
+        for neighbor in cardinal_neighbors(map, idx) {
+            if map.tiles[neighbor].tile_type == TileType::Brush && rng.gen::<f32>() < BURN_CHANCE {
+                map.tiles[neighbor] = named_tile("Stone Floor");
+            }
+        }
+    }
+}
+
+/// Burns every being caught in lava
+fn scorch_beings(state: &mut State) {
+    let turn = state.turn_counter;
+    let in_lava: Vec<(Entity, bool)> = state
+        .world
+        .query::<(&Position, Option<&Player>)>()
+        .with::<&CombatStats>()
+        .iter()
+        .filter(|(_, (pos, _))| {
+            matches!(
+                state.map.fluids.get(pos.0.to_index(state.map.width)),
+                Some(Some(Fluid {
+                    kind: FluidKind::Lava,
+                    ..
+                }))
+            )
+        })
+        .map(|(e, (_, player))| (e, player.is_some()))
+        .collect();
+
+    for (being, is_player) in in_lava {
+        let slain = match state.world.get::<&mut CombatStats>(being) {
+            Ok(mut stats) if stats.health > 0 => {
+                stats.health = stats.health.saturating_sub(LAVA_DAMAGE);
+                stats.health == 0
+            }
+            _ => continue,
+        };
+        if is_player {
+            state
+                .message_log
+                .push(Message::new("You are burning in the lava!".to_string(), turn));
+        } else if slain {
+            if let Ok(breed) = state.world.get::<&Breed>(being) {
+                let seen = state
+                    .world
+                    .get::<&Position>(being)
+                    .is_ok_and(|pos| state.visible.get(pos.0.to_index(state.map.width)) == Some(&true));
+                if seen {
+                    state
+                        .message_log
+                        .push(Message::new(format!("The {} burns up in the lava", breed.name), turn));
+                }
+            }
+        }
+    }
+
+    if player_is_dead(state) && state.record.killer.is_none() {
+        state.record.killer = Some("lava".to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 6x1 open corridor holding the given fluids
+    fn corridor(fluids: Vec<Option<Fluid>>) -> Map {
+        let mut map = Map::test_strip(6, 1, &[TileType::Floor; 6]);
+        map.fluids = fluids;
+        map
+    }
+
+    fn water(level: u8) -> Option<Fluid> {
+        Some(Fluid {
+            kind: FluidKind::Water,
+            level,
+        })
+    }
+
+    fn lava(level: u8) -> Option<Fluid> {
+        Some(Fluid {
+            kind: FluidKind::Lava,
+            level,
+        })
+    }
+
+    fn total(fluids: &[Option<Fluid>]) -> u32 {
+        fluids.iter().flatten().map(|fluid| fluid.level as u32).sum()
+    }
+
+    #[test]
+    fn water_levels_out() {
+        let mut map = corridor(vec![water(MAX_LEVEL), None, None, None, None, None]);
+        for _ in 0..20 {
+            map.fluids = spread(&map, true).0;
+        }
+        assert_eq!(total(&map.fluids), MAX_LEVEL as u32);
+        let levels: Vec<u8> = map.fluids.iter().map(|fluid| fluid.map_or(0, |f| f.level)).collect();
+        assert!(levels.windows(2).all(|pair| pair[0].abs_diff(pair[1]) <= 1));
+        assert!(levels.iter().filter(|&&level| level > 0).count() >= 4);
+    }
+
+    #[test]
+    fn settled_fluid_stays_put() {
+        let map = corridor(vec![water(1), water(1), None, None, None, None]);
+        assert_eq!(spread(&map, true).0, map.fluids);
+    }
+
+    #[test]
+    fn water_cools_lava() {
+        let map = corridor(vec![water(4), lava(1), None, None, None, None]);
+        let (fluids, cooled) = spread(&map, true);
+        assert_eq!(cooled, vec![1]);
+        assert_eq!(fluids[1], None);
+        assert_eq!(fluids[0], water(3));
+    }
+}
@@ -1,6 +1,9 @@
 use bracket_pathfinding::prelude::*;
 
-use crate::{Player, Position, State};
+use crate::{
+    status::{StatusEffects, StatusKind},
+    Position, State,
+};
 
 pub struct ViewShed {
     pub visible_tiles: Vec<Point>,
@@ -18,29 +21,25 @@ impl ViewShed {
     }
 }
 
+/// Recalculates the line of sight of anything that has moved
+/// What the player actually sees also depends on the light, see `lighting::update_lighting`
 pub fn update_vision(state: &mut State) {
-    for (_, (viewshed, pos, player)) in state
+    for (_, (viewshed, pos, effects)) in state
         .world
-        .query::<(&mut ViewShed, &Position, Option<&Player>)>()
+        .query::<(&mut ViewShed, &Position, Option<&StatusEffects>)>()
         .iter()
     {
         if !viewshed.dirty {
-            return;
+            continue;
         }
         viewshed.dirty = false;
         viewshed.visible_tiles.clear();
-        viewshed.visible_tiles = field_of_view(pos.0, viewshed.range as i32, &state.map);
+        // the blind can only feel out what is right next to them
+        let range = match effects.is_some_and(|effects| effects.has(StatusKind::Blinded)) {
+            true => 1,
+            false => viewshed.range,
+        };
+        viewshed.visible_tiles = field_of_view(pos.0, range as i32, &state.map);
         viewshed.visible_tiles.retain(|p| state.map.within_bounds(*p));
-
-        if let Some(_) = player {
-            for tile in state.visible.iter_mut() {
-                *tile = false;
-            }
-            for point in viewshed.visible_tiles.iter() {
-                let idx = point.to_index(state.map.width);
-                state.map.discovered[idx] = true;
-                state.visible[idx] = true;
-            }
-        }
     }
 }
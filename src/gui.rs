@@ -4,7 +4,15 @@ use hecs::{With, World};
 use crate::{
     actor::{Player, Position},
     combat::CombatStats,
+    experience::Experience,
+    hunger::{player_hunger, HungerState},
+    item::Inventory,
+    lighting::Torch,
     map::Map,
+    oxygen::{air_at, Oxygen, BREATHABLE},
+    shop::Purse,
+    status::{StatusEffects, StatusKind},
+    structure::{stability_near, Stability},
     State,
 };
 
@@ -36,6 +44,20 @@ fn draw_right_box(ctx: &mut BTerm, state: &State, screenheight: usize) {
     if let Some((pos, idx)) = get_player_pos(&state.world, &state.map) {
         ctx.print(right_map_edge_x, 5, format!("X: {} Y: {}", pos.x(), pos.y()));
         ctx.print(right_map_edge_x, 6, format!("Tile Index: {}", idx));
+
+        let stability = stability_near(&state.map, pos.0);
+        let color = match stability {
+            Stability::Stable => WHITE,
+            Stability::Unstable => ORANGE,
+            Stability::Collapsing => RED,
+        };
+        ctx.print_color(
+            right_map_edge_x,
+            8,
+            color,
+            BLACK,
+            format!("Ceiling: {}", stability.describe()),
+        );
     }
 
     if let Some(stats) = get_player_stats(&state.world) {
@@ -49,10 +71,69 @@ fn draw_right_box(ctx: &mut BTerm, state: &State, screenheight: usize) {
     }
 
     ctx.print(right_map_edge_x, 7, format!("Depth: {}", state.map.depth));
+
+    if let Some((_, (pos, oxygen))) = state.world.query::<With<(&Position, &Oxygen), &Player>>().iter().next() {
+        let air = air_at(&state.map, pos.0.to_index(state.map.width));
+        draw_oxygen_bar(ctx, oxygen, air, Point::new(right_map_edge_x, 11));
+    }
+
+    if let Some(hunger) = player_hunger(&state.world) {
+        let color = match hunger {
+            HungerState::WellFed => GREEN,
+            HungerState::Fed => WHITE,
+            HungerState::Hungry => YELLOW,
+            HungerState::Weak => ORANGE,
+            HungerState::Starving => RED,
+        };
+        ctx.print_color(right_map_edge_x, 12, color, BLACK, hunger.describe());
+    }
+
+    if let Some((_, torch)) = state.world.query::<With<&Torch, &Player>>().iter().next() {
+        let color = match torch.fuel {
+            0 => RED,
+            f if f <= torch.max_fuel / 5 => ORANGE,
+            _ => WHITE,
+        };
+        ctx.print_color(
+            right_map_edge_x,
+            13,
+            color,
+            BLACK,
+            format!("Torch: {}/{}", torch.fuel, torch.max_fuel),
+        );
+    }
+
+    if let Some((_, effects)) = state.world.query::<With<&StatusEffects, &Player>>().iter().next() {
+        // only four tags fit across the panel
+        for (offset, effect) in effects.0.iter().take(4).enumerate() {
+            let color = match effect.kind {
+                StatusKind::Regenerating => GREEN,
+                _ => ORANGE,
+            };
+            ctx.print_color(right_map_edge_x + offset * 4, 14, color, BLACK, effect.kind.tag());
+        }
+    }
+
+    if let Some((_, exp)) = state.world.query::<With<&Experience, &Player>>().iter().next() {
+        ctx.print(right_map_edge_x, 15, format!("Level: {}", exp.level));
+        ctx.print(right_map_edge_x, 16, format!("XP: {}/{}", exp.xp, exp.next_threshold()));
+    }
+
+    if let Some((_, purse)) = state.world.query::<With<&Purse, &Player>>().iter().next() {
+        ctx.print(right_map_edge_x, 17, format!("Coins: {}", purse.0));
+    }
+
+    if let Some((_, inventory)) = state.world.query::<With<&Inventory, &Player>>().iter().next() {
+        ctx.print(right_map_edge_x, 18, "Inventory:");
+        for (offset, (name, count)) in inventory.counts().iter().enumerate() {
+            ctx.print(right_map_edge_x, 19 + offset, format!("{count}x {name}"));
+        }
+    }
 }
 
 /// A fully customizable bar that splits between two characters with custom colors
 type ColoredChar = (char, RGBA, RGBA);
+#[allow(clippy::too_many_arguments)]
 fn draw_horizontal_split_bar(
     ctx: &mut BTerm,
     n: u32,
@@ -91,6 +172,25 @@ fn draw_hp_bar(ctx: &mut BTerm, name: impl ToString, hp: u32, max_hp: u32, start
     );
 }
 
+/// Shows how much breath the player has left, the label warns when the air around them is bad
+fn draw_oxygen_bar(ctx: &mut BTerm, oxygen: &Oxygen, air: u32, starting_pos: Point) {
+    let label_color = match air >= BREATHABLE {
+        true => WHITE,
+        false => ORANGE,
+    };
+    ctx.print_color(starting_pos.x, starting_pos.y, label_color, BLACK, "O2:");
+    draw_horizontal_split_bar(
+        ctx,
+        oxygen.current,
+        oxygen.max,
+        starting_pos.x + 3,
+        starting_pos.y,
+        15,
+        ('o', RGBA::named(SKY_BLUE), RGBA::named(BLACK)),
+        ('o', RGBA::named(DARKSLATEGRAY), RGBA::named(BLACK)),
+    );
+}
+
 fn get_player_pos(world: &World, map: &Map) -> Option<(Position, usize)> {
     if let Some((_, player_pos)) = world.query::<With<&Position, &Player>>().iter().next() {
         return Some((player_pos.clone(), player_pos.0.to_index(map.width)));
@@ -100,7 +200,7 @@ fn get_player_pos(world: &World, map: &Map) -> Option<(Position, usize)> {
 
 fn get_player_stats(world: &World) -> Option<CombatStats> {
     if let Some((_, player_combat_stats)) = world.query::<With<&CombatStats, &Player>>().iter().next() {
-        return Some(player_combat_stats.clone());
+        return Some(*player_combat_stats);
     }
     None
 }
@@ -0,0 +1,225 @@
+/*  Hunger.rs keeps the player fed, or not.
+    The player grows hungrier with every turn and must eat food they find, buy or forage from lush brush.
+    Going without makes them slow and eventually starving eats away at their health.
+*/
+use hecs::{With, World};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actor::{Player, Position},
+    combat::CombatStats,
+    data_read::{item_nutrition, named_tile},
+    fov::ViewShed,
+    item::{Inventory, Item},
+    map::TileType,
+    messagelog::Message,
+    morgue::player_is_dead,
+    State,
+};
+
+/// How full the player can get
+pub const PLAYER_SATIATION: u32 = 1000;
+/// Satiation lost every turn
+const HUNGER_PER_TURN: u32 = 1;
+/// Health lost every turn spent starving
+const STARVATION_DAMAGE: u32 = 1;
+/// What is picked from a patch of lush brush
+pub const BERRIES: &str = "Cave Berries";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Hunger {
+    pub satiation: u32,
+    pub max: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum HungerState {
+    WellFed,
+    Fed,
+    Hungry,
+    Weak,
+    Starving,
+}
+
+impl Hunger {
+    pub fn new(max: u32) -> Self {
+        Self { satiation: max, max }
+    }
+
+    pub fn state(&self) -> HungerState {
+        match self.satiation * 100 / self.max.max(1) {
+            0 => HungerState::Starving,
+            s if s <= 15 => HungerState::Weak,
+            s if s <= 30 => HungerState::Hungry,
+            s if s <= 80 => HungerState::Fed,
+            _ => HungerState::WellFed,
+        }
+    }
+
+    /// Fills up the player, returns false when they are too full to eat
+    pub fn eat(&mut self, nutrition: u32) -> bool {
+        if self.satiation >= self.max {
+            return false;
+        }
+        self.satiation = (self.satiation + nutrition).min(self.max);
+        true
+    }
+}
+
+impl HungerState {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            HungerState::WellFed => "Well Fed",
+            HungerState::Fed => "Fed",
+            HungerState::Hungry => "Hungry",
+            HungerState::Weak => "Weak",
+            HungerState::Starving => "Starving",
+        }
+    }
+
+    /// A weak or starving player takes longer to do anything
+    pub fn action_cost(&self, cost: u32) -> u32 {
+        match self {
+            HungerState::Weak | HungerState::Starving => cost * 5 / 4,
+            _ => cost,
+        }
+    }
+
+    fn warning(&self) -> Option<&'static str> {
+        match self {
+            HungerState::Hungry => Some("Your stomach grumbles, you are getting hungry"),
+            HungerState::Weak => Some("You feel weak with hunger"),
+            HungerState::Starving => Some("You are starving!"),
+            _ => None,
+        }
+    }
+}
+
+/// How hungry the player is
+pub fn player_hunger(world: &World) -> Option<HungerState> {
+    world
+        .query::<With<&Hunger, &Player>>()
+        .iter()
+        .next()
+        .map(|(_, hunger)| hunger.state())
+}
+
+/// The player grows hungrier each turn, warned as they get worse and hurt once they are starving
+pub fn tick_hunger(state: &mut State) {
+    let turn = state.turn_counter;
+    if let Some((_, (hunger, stats))) = state
+        .world
+        .query_mut::<With<(&mut Hunger, &mut CombatStats), &Player>>()
+        .into_iter()
+        .next()
+    {
+        let before = hunger.state();
+        hunger.satiation = hunger.satiation.saturating_sub(HUNGER_PER_TURN);
+        let after = hunger.state();
+        if after != before {
+            if let Some(warning) = after.warning() {
+                state.message_log.push(Message::new(warning.to_string(), turn));
+            }
+        }
+        if after == HungerState::Starving {
+            stats.health = stats.health.saturating_sub(STARVATION_DAMAGE);
+        }
+    }
+
+    if player_is_dead(state) && state.record.killer.is_none() {
+        state.record.killer = Some("starvation".to_string());
+    }
+}
+
+/// Eats the first food the player is carrying, returns true if they ate
+pub fn eat_food(state: &mut State) -> bool {
+    let turn = state.turn_counter;
+    let Some((_, (hunger, inventory))) = state
+        .world
+        .query_mut::<With<(&mut Hunger, &mut Inventory), &Player>>()
+        .into_iter()
+        .next()
+    else {
+        return false;
+    };
+
+    let food = inventory
+        .items
+        .iter()
+        .enumerate()
+        .find_map(|(idx, item)| item_nutrition(&item.name).map(|nutrition| (idx, nutrition)));
+    let Some((idx, nutrition)) = food else {
+        state
+            .message_log
+            .push(Message::new("You have nothing to eat".to_string(), turn));
+        return false;
+    };
+
+    if !hunger.eat(nutrition) {
+        state
+            .message_log
+            .push(Message::new("You are too full to eat".to_string(), turn));
+        return false;
+    }
+    let eaten = inventory.items.remove(idx);
+    state
+        .message_log
+        .push(Message::new(format!("You eat the {}", eaten.name), turn));
+    true
+}
+
+/// Picks the berries off the lush brush the player stands in, leaving trampled grass behind
+/// Returns true if there was anything to forage
+pub fn forage(state: &mut State) -> bool {
+    let idx = match state.world.query::<With<&Position, &Player>>().iter().next() {
+        Some((_, pos)) => pos.0.to_index(state.map.width),
+        None => return false,
+    };
+    if state.map.tiles[idx].tile_type != TileType::Brush {
+        return false;
+    }
+
+    state.map.tiles[idx] = named_tile("Grass Floor");
+    if let Some((_, (inventory, view))) = state
+        .world
+        .query_mut::<With<(&mut Inventory, &mut ViewShed), &Player>>()
+        .into_iter()
+        .next()
+    {
+        inventory.items.push(Item::new(BERRIES));
+        view.dirty = true;
+    }
+    state.message_log.push(Message::new(
+        format!("You pick some {BERRIES} from the brush"),
+        state.turn_counter,
+    ));
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hunger_worsens_as_satiation_drops() {
+        let mut hunger = Hunger::new(1000);
+        assert_eq!(hunger.state(), HungerState::WellFed);
+        hunger.satiation = 500;
+        assert_eq!(hunger.state(), HungerState::Fed);
+        hunger.satiation = 250;
+        assert_eq!(hunger.state(), HungerState::Hungry);
+        hunger.satiation = 100;
+        assert_eq!(hunger.state(), HungerState::Weak);
+        hunger.satiation = 0;
+        assert_eq!(hunger.state(), HungerState::Starving);
+    }
+
+    #[test]
+    fn eating_fills_up_to_max() {
+        let mut hunger = Hunger::new(1000);
+        assert!(!hunger.eat(100));
+        hunger.satiation = 950;
+        assert!(hunger.eat(100));
+        assert_eq!(hunger.satiation, 1000);
+    }
+}
@@ -1,7 +1,25 @@
 use crate::{
     actor::{change_floor, mine, player_attack, player_bump, MoveResult, Position},
+    consumable::open_use_menu,
+    crafting::{open_crafting, Station},
+    dialogue::start_conversation,
+    energy::{
+        ATTACK_COST, EAT_COST, FORAGE_COST, MINE_COST, PICKUP_COST, REFUEL_COST, STEP_COST, SWIM_COST, WAIT_COST,
+    },
+    experience::credit_player_kill,
+    hunger::{eat_food, forage},
+    item::pick_up_items,
+    lighting::refuel_torch,
+    map::Destructible,
     messagelog::Message,
+    noise::{player_noise, COMBAT_NOISE, STEP_NOISE},
+    ranged::start_targeting,
+    rest::start_resting,
+    shop::open_shop,
     state::PlayerResponse,
+    status::{player_has_status, StatusKind},
+    structure::check_cave_in,
+    water::{player_in_water, sink_heavy_items},
     RunState, State,
 };
 use bracket_terminal::prelude::{BTerm, VirtualKeyCode};
@@ -11,22 +29,61 @@ use bracket_terminal::prelude::{BTerm, VirtualKeyCode};
 pub fn handle_player_action(state: &mut State, action: Action) -> PlayerResponse {
     let turn_sent = state.turn_counter;
 
+    // a stunned player loses whatever they were about to do
+    if !matches!(action, Action::None | Action::SaveGame) && player_has_status(&state.world, StatusKind::Stunned) {
+        state
+            .message_log
+            .push(Message::new("You are too stunned to act".to_string(), turn_sent));
+        return PlayerResponse::TurnAdvance(WAIT_COST);
+    }
+
     match action {
         Action::None => PlayerResponse::Waiting,
-        Action::Wait => PlayerResponse::TurnAdvance,
+        Action::Wait => PlayerResponse::TurnAdvance(WAIT_COST),
         Action::Direction { delta } => match player_bump(&mut state.map, &mut state.world, delta.0) {
-            MoveResult::Moved(_) => PlayerResponse::TurnAdvance,
+            MoveResult::Moved => {
+                player_noise(state, STEP_NOISE);
+                if player_in_water(state) {
+                    sink_heavy_items(state);
+                    return PlayerResponse::TurnAdvance(SWIM_COST);
+                }
+                PlayerResponse::TurnAdvance(STEP_COST)
+            }
             MoveResult::InvalidMove(msg) => {
                 state.message_log.push(Message::new(msg, turn_sent));
                 PlayerResponse::Waiting
             }
             MoveResult::Attack(target) => {
-                player_attack(&mut state.world, &mut state.message_log, target, turn_sent);
-                PlayerResponse::TurnAdvance
+                player_noise(state, COMBAT_NOISE);
+                if player_attack(&mut state.world, &mut state.message_log, target, turn_sent) {
+                    credit_player_kill(state, target);
+                }
+                PlayerResponse::TurnAdvance(ATTACK_COST)
+            }
+            MoveResult::Talk(target) => {
+                if let Some(menu) = open_shop(&state.world, target) {
+                    return PlayerResponse::StateChange(RunState::Shop(menu));
+                }
+                match start_conversation(&state.world, target) {
+                    Some(conversation) => PlayerResponse::StateChange(RunState::Dialogue(conversation)),
+                    None => PlayerResponse::Waiting,
+                }
+            }
+            MoveResult::Mine(Destructible::Entity(station)) if state.world.get::<&Station>(station).is_ok() => {
+                PlayerResponse::StateChange(open_crafting())
             }
             MoveResult::Mine(destructible) => {
-                if mine(&mut state.map, &mut state.world, destructible, delta.0) {
-                    PlayerResponse::TurnAdvance
+                if mine(
+                    &mut state.map,
+                    &mut state.world,
+                    destructible,
+                    delta.0,
+                    &mut state.message_log,
+                    &mut state.noises,
+                    turn_sent,
+                ) {
+                    check_cave_in(state);
+                    PlayerResponse::TurnAdvance(MINE_COST)
                 } else {
                     PlayerResponse::Waiting
                 }
@@ -40,6 +97,36 @@ pub fn handle_player_action(state: &mut State, action: Action) -> PlayerResponse
             true => PlayerResponse::StateChange(RunState::NextLevel(state.map.depth + 1)),
             false => PlayerResponse::Waiting,
         },
+        Action::PickUp => {
+            if forage(state) {
+                return PlayerResponse::TurnAdvance(FORAGE_COST);
+            }
+            match pick_up_items(state) {
+                true => PlayerResponse::TurnAdvance(PICKUP_COST),
+                false => PlayerResponse::Waiting,
+            }
+        }
+        Action::Eat => match eat_food(state) {
+            true => PlayerResponse::TurnAdvance(EAT_COST),
+            false => PlayerResponse::Waiting,
+        },
+        Action::Refuel => match refuel_torch(state) {
+            true => PlayerResponse::TurnAdvance(REFUEL_COST),
+            false => PlayerResponse::Waiting,
+        },
+        Action::Rest => match start_resting(state) {
+            Some(resting) => PlayerResponse::StateChange(resting),
+            None => PlayerResponse::Waiting,
+        },
+        Action::Fire => match start_targeting(state) {
+            Some(targeting) => PlayerResponse::StateChange(targeting),
+            None => PlayerResponse::Waiting,
+        },
+        Action::Use => match open_use_menu(state) {
+            Some(menu) => PlayerResponse::StateChange(menu),
+            None => PlayerResponse::Waiting,
+        },
+        Action::Craft => PlayerResponse::StateChange(open_crafting()),
         Action::SaveGame => PlayerResponse::StateChange(RunState::SaveGame),
     }
 }
@@ -50,6 +137,13 @@ pub enum Action {
     Descend,
     Ascend,
     Wait,
+    PickUp,
+    Eat,
+    Refuel,
+    Rest,
+    Fire,
+    Use,
+    Craft,
     SaveGame, // this will probably change to a menu
 }
 
@@ -83,6 +177,13 @@ pub fn player_input(ctx: &mut BTerm) -> Action {
             VirtualKeyCode::Comma => Action::Ascend,
             VirtualKeyCode::Period => Action::Descend,
             VirtualKeyCode::Space => Action::Wait,
+            VirtualKeyCode::G => Action::PickUp,
+            VirtualKeyCode::E => Action::Eat,
+            VirtualKeyCode::R => Action::Refuel,
+            VirtualKeyCode::Z => Action::Rest,
+            VirtualKeyCode::F => Action::Fire,
+            VirtualKeyCode::Q => Action::Use,
+            VirtualKeyCode::C => Action::Craft,
             VirtualKeyCode::Escape => Action::SaveGame,
             _ => Action::None,
         }
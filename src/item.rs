@@ -1,4 +1,83 @@
-#[derive(Clone)]
+use hecs::{Entity, With};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actor::{Player, Position},
+    messagelog::Message,
+    State,
+};
+
+/// Something that can be carried, when it has a Position it is lying on the ground
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Item {
-    name: String,
+    pub name: String,
+}
+
+impl Item {
+    pub fn new(name: impl ToString) -> Self {
+        Self { name: name.to_string() }
+    }
+}
+
+/// Holds the items a being is carrying
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Inventory {
+    pub items: Vec<Item>,
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    /// Groups the carried items by name alongside how many of each are held, in the order first picked up
+    pub fn counts(&self) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for item in self.items.iter() {
+            match counts.iter_mut().find(|(name, _)| *name == item.name) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((item.name.clone(), 1)),
+            }
+        }
+        counts
+    }
+}
+
+/// Moves every item the player is standing on into their inventory
+/// Returns true if anything was picked up
+pub fn pick_up_items(state: &mut State) -> bool {
+    let player_pos = match state.world.query::<With<&Position, &Player>>().iter().next() {
+        Some((_, pos)) => pos.0,
+        None => return false,
+    };
+
+    let mut on_ground: Vec<(Entity, Item)> = Vec::new();
+    for (e, (item, pos)) in state.world.query::<(&Item, &Position)>().iter() {
+        if pos.0 == player_pos {
+            on_ground.push((e, item.clone()));
+        }
+    }
+    if on_ground.is_empty() {
+        state.message_log.push(Message::new(
+            "There is nothing here to pick up".to_string(),
+            state.turn_counter,
+        ));
+        return false;
+    }
+
+    for (e, item) in on_ground {
+        if let Some((_, inventory)) = state
+            .world
+            .query_mut::<With<&mut Inventory, &Player>>()
+            .into_iter()
+            .next()
+        {
+            state
+                .message_log
+                .push(Message::new(format!("You picked up {}", item.name), state.turn_counter));
+            inventory.items.push(item);
+        }
+        let _ = state.world.despawn(e);
+    }
+    true
 }
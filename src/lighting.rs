@@ -0,0 +1,242 @@
+/*  Lighting.rs spreads light through the caves from anything that glows.
+    Near the surface daylight still filters down but the deep caves are pitch black, there the player only
+    sees what their own light or a glowing tile, a torch or lava lights up for them.
+*/
+use bracket_pathfinding::prelude::{field_of_view, DistanceAlg, Point};
+use bracket_terminal::prelude::RGB;
+use hecs::With;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actor::{Player, Position},
+    data_read::item_fuel,
+    fov::ViewShed,
+    item::Inventory,
+    map::Map,
+    messagelog::Message,
+    State,
+};
+
+/// Light radius of a freshly lit torch, as far as the player can see in the dark
+pub const TORCH_RADIUS: u32 = 8;
+/// Turns a full torch burns for
+pub const TORCH_FUEL: u32 = 500;
+/// Daylight lost every floor down from the surface
+const AMBIENT_LOSS_PER_DEPTH: f32 = 0.2;
+/// Tiles lit dimmer than this are too dark to make anything out
+pub const LIT_THRESHOLD: f32 = 0.15;
+
+/// Something that gives off light, glowing tiles carry one as well as entities
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct LightSource {
+    pub radius: u32,
+    pub color: RGB,
+}
+
+impl LightSource {
+    pub fn new(radius: u32, color: RGB) -> Self {
+        Self { radius, color }
+    }
+}
+
+/// The torch the player carries, it burns down a little every turn
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Torch {
+    pub fuel: u32,
+    pub max_fuel: u32,
+}
+
+impl Torch {
+    pub fn new(max_fuel: u32) -> Self {
+        Self {
+            fuel: max_fuel,
+            max_fuel,
+        }
+    }
+
+    /// How far the torch lights, shrinking as it burns down until only the tiles next to the player are lit
+    pub fn radius(&self) -> u32 {
+        if self.max_fuel == 0 {
+            return 1;
+        }
+        1 + ((TORCH_RADIUS - 1) * self.fuel).div_ceil(self.max_fuel)
+    }
+}
+
+/// How much daylight reaches a floor at some depth
+pub fn ambient_light(depth: usize) -> f32 {
+    (1.0 - depth as f32 * AMBIENT_LOSS_PER_DEPTH).max(0.0)
+}
+
+/// How brightly a tile is lit, the brightest channel of the light reaching it
+pub fn brightness(light: RGB) -> f32 {
+    light.r.max(light.g).max(light.b)
+}
+
+/// Light reaching a tile, maps that have not been lit yet only get daylight
+pub fn light_at(map: &Map, idx: usize) -> RGB {
+    match map.light.get(idx) {
+        Some(light) => *light,
+        None => {
+            let ambient = ambient_light(map.depth);
+            RGB::from_f32(ambient, ambient, ambient)
+        }
+    }
+}
+
+/// Burns the player's torch for a turn, their light and how far they can see shrink as it burns down
+/// Where daylight still reaches the player can see as far as ever
+pub fn burn_torch(state: &mut State) {
+    let turn = state.turn_counter;
+    let daylight = ambient_light(state.map.depth) >= LIT_THRESHOLD;
+    if let Some((_, (torch, light, view))) = state
+        .world
+        .query_mut::<With<(&mut Torch, &mut LightSource, &mut ViewShed), &Player>>()
+        .into_iter()
+        .next()
+    {
+        let was_low = torch.fuel <= torch.max_fuel / 5;
+        torch.fuel = torch.fuel.saturating_sub(1);
+        if !was_low && torch.fuel <= torch.max_fuel / 5 {
+            state
+                .message_log
+                .push(Message::new("Your torch is burning low".to_string(), turn));
+        } else if torch.fuel == 0 && light.radius > 1 {
+            state.message_log.push(Message::new(
+                "Your torch gutters out, darkness closes in".to_string(),
+                turn,
+            ));
+        }
+
+        light.radius = torch.radius();
+        let sight = if daylight { TORCH_RADIUS } else { light.radius };
+        if view.range != sight {
+            view.range = sight;
+            view.dirty = true;
+        }
+    }
+}
+
+/// Feeds the first fuel the player carries to their torch, returns true if it was refueled
+pub fn refuel_torch(state: &mut State) -> bool {
+    let turn = state.turn_counter;
+    let Some((_, (torch, inventory))) = state
+        .world
+        .query_mut::<With<(&mut Torch, &mut Inventory), &Player>>()
+        .into_iter()
+        .next()
+    else {
+        return false;
+    };
+
+    if torch.fuel >= torch.max_fuel {
+        state
+            .message_log
+            .push(Message::new("Your torch is already burning bright".to_string(), turn));
+        return false;
+    }
+    let fuel = inventory
+        .items
+        .iter()
+        .enumerate()
+        .find_map(|(idx, item)| item_fuel(&item.name).map(|fuel| (idx, fuel)));
+    let Some((idx, fuel)) = fuel else {
+        state
+            .message_log
+            .push(Message::new("You have nothing to feed your torch".to_string(), turn));
+        return false;
+    };
+
+    let burned = inventory.items.remove(idx);
+    torch.fuel = (torch.fuel + fuel).min(torch.max_fuel);
+    state.message_log.push(Message::new(
+        format!("You feed the {} to your torch", burned.name),
+        turn,
+    ));
+    true
+}
+
+/// Relights the map from every source and reveals to the player whatever in their sight is lit
+/// Lights only move or change as turns pass, so the map is left as it is until it is marked light_dirty
+pub fn update_lighting(state: &mut State) {
+    if !state.map.light_dirty && state.map.light.len() == state.map.tiles.len() {
+        return;
+    }
+    state.map.light_dirty = false;
+
+    let ambient = ambient_light(state.map.depth);
+    let mut light = vec![RGB::from_f32(ambient, ambient, ambient); state.map.tiles.len()];
+
+    for (idx, tile) in state.map.tiles.iter().enumerate() {
+        if let Some(source) = tile.light {
+            shine(&state.map, &mut light, state.map.idx_to_point(idx), &source);
+        }
+    }
+    for (_, (pos, source)) in state.world.query::<(&Position, &LightSource)>().iter() {
+        shine(&state.map, &mut light, pos.0, source);
+    }
+    state.map.light = light;
+
+    reveal_lit_tiles(state);
+}
+
+/// Adds the light of a source to every tile it can reach, fading out towards the edge of its radius
+fn shine(map: &Map, light: &mut [RGB], center: Point, source: &LightSource) {
+    let reach = source.radius as f32 + 1.0;
+    for pt in field_of_view(center, source.radius as i32, map) {
+        if !map.within_bounds(pt) {
+            continue;
+        }
+        let falloff = 1.0 - DistanceAlg::Pythagoras.distance2d(center, pt) / reach;
+        let idx = pt.to_index(map.width);
+        let lit = light[idx] + source.color * falloff.max(0.0);
+        light[idx] = RGB::from_f32(lit.r.min(1.0), lit.g.min(1.0), lit.b.min(1.0));
+    }
+}
+
+/// The player sees only the tiles in their line of sight that are bright enough to make out
+fn reveal_lit_tiles(state: &mut State) {
+    for tile in state.visible.iter_mut() {
+        *tile = false;
+    }
+
+    if let Some((_, view)) = state.world.query::<With<&ViewShed, &Player>>().iter().next() {
+        for point in view.visible_tiles.iter() {
+            let idx = point.to_index(state.map.width);
+            if brightness(light_at(&state.map, idx)) >= LIT_THRESHOLD {
+                state.map.discovered[idx] = true;
+                state.visible[idx] = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deep_floors_are_dark() {
+        assert_eq!(ambient_light(0), 1.0);
+        assert!(ambient_light(2) > LIT_THRESHOLD);
+        assert_eq!(ambient_light(10), 0.0);
+    }
+
+    #[test]
+    fn torch_shrinks_as_it_burns() {
+        let mut torch = Torch::new(100);
+        assert_eq!(torch.radius(), TORCH_RADIUS);
+        torch.fuel = 50;
+        assert!(torch.radius() < TORCH_RADIUS);
+        torch.fuel = 1;
+        assert_eq!(torch.radius(), 2);
+        torch.fuel = 0;
+        assert_eq!(torch.radius(), 1);
+    }
+
+    #[test]
+    fn brightness_is_the_strongest_channel() {
+        assert_eq!(brightness(RGB::from_f32(0.2, 0.8, 0.1)), 0.8);
+        assert_eq!(brightness(RGB::new()), 0.0);
+    }
+}
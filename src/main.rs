@@ -4,7 +4,8 @@ use rand::{seq::SliceRandom, Rng};
 use std::fs;
 
 mod data_read;
-use data_read::{named_living_builder, ENTITY_DB};
+mod dialogue;
+use data_read::{named_item_builder, named_living_builder, ENTITY_DB};
 mod gui;
 mod map;
 mod menu;
@@ -12,19 +13,51 @@ mod messagelog;
 mod monster;
 mod prefab;
 mod worldgen;
-use map::Map;
+use map::{FloorKind, Map, TileType};
 use worldgen::generate_map;
 mod actor;
+mod breakable;
+mod burrow;
+use breakable::{spawn_breakable, Breakable};
+use crafting::place_stations;
 mod fov;
+mod hunger;
 mod item;
-use actor::{CharSprite, Player, Position};
+mod lighting;
+use actor::{CharSprite, Position};
 mod combat;
-use combat::CombatStats;
+use combat::{seed_combat_rng, CombatStats};
+use equipment::Equipment;
+use experience::Experience;
+use hunger::{Hunger, PLAYER_SATIATION};
+use item::Inventory;
+use lighting::{Torch, TORCH_FUEL};
+use oxygen::{Oxygen, PLAYER_OXYGEN};
+use shop::{Purse, STARTING_COINS};
 mod config;
+mod consumable;
+mod crafting;
+mod effects;
+mod energy;
+mod equipment;
+mod experience;
+mod faction;
+mod fluid;
 mod input;
 mod map_scanning;
+mod morgue;
+mod navigation;
+mod noise;
+mod oxygen;
+mod ranged;
+mod rest;
 mod save_system;
+mod shop;
 mod state;
+mod status;
+mod structure;
+mod town;
+mod water;
 
 use crate::{
     config::Config,
@@ -76,22 +109,73 @@ fn main() -> BError {
 
 /// Creates a new map and setups world for the start of a fresh run
 pub fn start_new_game(world: &mut World, seed: u64) -> Map {
+    seed_combat_rng(seed);
     let (mut map, player_start) = generate_map(seed, 0);
     add_player_to_room(world, player_start);
     furnish_map(world, &mut map);
     map
 }
 
-/// Adds the life and decor to the map
+/// Adds the life and decor to the map, towns are a safe haven so only their residents are added
+/// Breakables and items a floor was left with are put back where they were
 fn furnish_map(world: &mut World, map: &mut Map) {
+    for item in std::mem::take(&mut map.ground_items) {
+        if let Some(mut eb) = named_item_builder(&ENTITY_DB.lock().unwrap(), &item.name, Position(item.home)) {
+            world.spawn(eb.build());
+        }
+    }
+
+    if let FloorKind::Town { residents, fixtures } = &map.kind {
+        for resident in residents.clone() {
+            let e_builder = named_living_builder(&ENTITY_DB.lock().unwrap(), &resident.name, Position(resident.home));
+            if let Some(mut eb) = e_builder {
+                map.beings[resident.home.to_index(map.width)] = Some(world.spawn(eb.build()));
+            }
+        }
+        for fixture in fixtures.clone() {
+            spawn_breakable(world, map, &fixture.name, Position(fixture.home));
+        }
+        return;
+    }
+
+    match map.breakables.take() {
+        Some(breakables) => {
+            for breakable in breakables {
+                let spawned = spawn_breakable(world, map, &breakable.name, Position(breakable.home));
+                if let (Some(e), Some(hp)) = (spawned, breakable.hp) {
+                    if let Ok(mut stored) = world.get::<&mut Breakable>(e) {
+                        stored.hp = hp;
+                    }
+                }
+            }
+        }
+        None => {
+            add_breakables_to_rooms(world, map);
+            place_stations(world, map);
+        }
+    }
     add_beings_to_rooms(world, map);
+    add_swimmers_to_water(world, map);
+    add_wanderer_to_room(world, map);
 }
 
 pub fn add_player_to_room(world: &mut World, player_start: Position) {
     let player_builder = named_living_builder(&ENTITY_DB.lock().unwrap(), "Player", player_start);
     if let Some(mut pb) = player_builder {
         let p_entity = world.spawn(pb.build());
-        match world.insert(p_entity, (CombatStats::new(200, 10, 1),)) {
+        match world.insert(
+            p_entity,
+            (
+                CombatStats::new(200, 10, 1),
+                Inventory::new(),
+                Experience::new(),
+                Purse(STARTING_COINS),
+                Oxygen::new(PLAYER_OXYGEN),
+                Hunger::new(PLAYER_SATIATION),
+                Torch::new(TORCH_FUEL),
+                Equipment::default(),
+            ),
+        ) {
             Ok(..) => {}
             Err(e) => {
                 println!("{}", e);
@@ -101,7 +185,7 @@ pub fn add_player_to_room(world: &mut World, player_start: Position) {
 }
 
 fn add_beings_to_rooms(world: &mut World, map: &mut Map) {
-    let beings = vec!["Centipede", "Mole", "Star Nosed Mole"];
+    let beings = ["Centipede", "Mole", "Star Nosed Mole", "Cave Spider"];
     for room in map.rooms.iter() {
         let monster_spawns_per_room = room.tiles.len() / 15;
         for _ in 0..monster_spawns_per_room {
@@ -111,6 +195,9 @@ fn add_beings_to_rooms(world: &mut World, map: &mut Map) {
             }
 
             let being_pos = room.get_random_point();
+            if map.destructibles[being_pos.to_index(map.width)].is_some() {
+                continue;
+            }
             let being_name = beings.choose(&mut rand::thread_rng()).unwrap();
             let e_builder = named_living_builder(&ENTITY_DB.lock().unwrap(), being_name, Position(being_pos));
             if let Some(mut eb) = e_builder {
@@ -120,3 +207,73 @@ fn add_beings_to_rooms(world: &mut World, map: &mut Map) {
         }
     }
 }
+
+/// Lakes and rivers have things living in them too
+fn add_swimmers_to_water(world: &mut World, map: &mut Map) {
+    let water: Vec<usize> = (0..map.tiles.len())
+        .filter(|&idx| map.tiles[idx].tile_type == TileType::Water)
+        .collect();
+    for _ in 0..water.len() / 40 {
+        let chance: f32 = rand::thread_rng().gen();
+        if chance > 0.5 {
+            continue;
+        }
+
+        let Some(&idx) = water.choose(&mut rand::thread_rng()) else {
+            return;
+        };
+        if map.beings[idx].is_some() {
+            continue;
+        }
+        let e_builder = named_living_builder(&ENTITY_DB.lock().unwrap(), "Cave Eel", map.idx_to_pos(idx));
+        if let Some(mut eb) = e_builder {
+            map.beings[idx] = Some(world.spawn(eb.build()));
+        }
+    }
+}
+
+/// Now and then a friendly face can be found wandering the caves
+fn add_wanderer_to_room(world: &mut World, map: &mut Map) {
+    let chance: f32 = rand::thread_rng().gen();
+    if chance > 0.5 {
+        return;
+    }
+
+    let wanderer_pos = match map.rooms.choose(&mut rand::thread_rng()) {
+        Some(room) => room.get_random_point(),
+        None => return,
+    };
+    let idx = wanderer_pos.to_index(map.width);
+    if map.destructibles[idx].is_some() || map.beings[idx].is_some() {
+        return;
+    }
+    let e_builder = named_living_builder(&ENTITY_DB.lock().unwrap(), "NPC Dude", Position(wanderer_pos));
+    if let Some(mut eb) = e_builder {
+        map.beings[idx] = Some(world.spawn(eb.build()));
+    }
+}
+
+fn add_breakables_to_rooms(world: &mut World, map: &mut Map) {
+    let breakables = ["Crate", "Old Table", "Mushroom Patch", "Crystal Formation"];
+    let mut spots = Vec::new();
+    for room in map.rooms.iter() {
+        let breakable_spawns_per_room = room.tiles.len() / 25;
+        for _ in 0..breakable_spawns_per_room {
+            let chance: f32 = rand::thread_rng().gen();
+            if chance > 0.4 {
+                continue;
+            }
+            spots.push(room.get_random_point());
+        }
+    }
+
+    for spot in spots {
+        let idx = spot.to_index(map.width);
+        // only plain open ground so stairs and entrances stay reachable
+        if map.tiles[idx].tile_type != TileType::Floor || map.destructibles[idx].is_some() {
+            continue;
+        }
+        let breakable_name = breakables.choose(&mut rand::thread_rng()).unwrap();
+        spawn_breakable(world, map, breakable_name, Position(spot));
+    }
+}
@@ -1,13 +1,22 @@
 /* Map.rs is the map generation code and data structures to hold information about the map
  */
-use crate::{actor::Position, worldgen::WorldRoom, CharSprite, Config};
+use crate::{
+    actor::Position,
+    fluid::Fluid,
+    lighting::{light_at, LightSource},
+    structure::{stability_at, Stability},
+    worldgen::WorldRoom,
+    CharSprite, Config,
+};
 use bracket_pathfinding::prelude::{Algorithm2D, BaseMap, DistanceAlg, SmallVec};
-use bracket_terminal::prelude::{BTerm, Point, PURPLE, WHITESMOKE};
+use bracket_terminal::prelude::{BTerm, Point, DARK_ORANGE, PURPLE, RGB, WHITESMOKE};
 use hecs::Entity;
 use serde::{Deserialize, Serialize};
 
 pub const MAP_WIDTH: usize = 100;
 pub const MAP_HEIGHT: usize = 70;
+/// How dark a barely lit tile is drawn
+const MIN_TINT: f32 = 0.3;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Map {
@@ -17,11 +26,57 @@ pub struct Map {
     pub height: usize,
     pub depth: usize,
     pub discovered: Vec<bool>, // Tiles that the player has previously seen
+    #[serde(default)]
+    pub excavated: Vec<bool>, // Tiles that were dug out rather than formed naturally
+    #[serde(default)]
+    pub kind: FloorKind,
+    #[serde(default)]
+    pub fluids: Vec<Option<Fluid>>, // water or lava held in each tile, rock included
+    #[serde(default)]
+    pub breakables: Option<Vec<StoredBreakable>>, // Breakables left standing, None until the floor is first furnished
+    #[serde(default)]
+    pub ground_items: Vec<Resident>, // Items left lying around while the player is elsewhere
 
     #[serde(skip)]
     pub beings: Vec<Option<Entity>>, // Whether or not a "being" entity is occupying a space
     #[serde(skip)]
     pub destructibles: Vec<Option<Destructible>>, // Whether or not a space contains a destructible thing
+    #[serde(skip)]
+    pub air: Vec<u32>, // How breathable each tile is, settled from the shape of the cave
+    #[serde(skip)]
+    pub air_dirty: bool, // Whether the shape of the cave changed since the air was last settled
+    #[serde(skip)]
+    pub light: Vec<RGB>, // Light reaching each tile, relit once a turn
+    #[serde(skip)]
+    pub light_dirty: bool, // Whether lights may have moved or changed since the map was last lit
+}
+
+/// What sort of place a floor is, decides what lives there
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub enum FloorKind {
+    #[default]
+    Cave,
+    Town {
+        residents: Vec<Resident>,
+        #[serde(default)]
+        fixtures: Vec<Resident>, // workbenches and such that stay put, they are spawned alongside the residents
+    }, // a safe haven, only its residents are spawned
+}
+
+/// Someone or something that belongs to a floor and is always found at the same spot
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Resident {
+    pub name: String,
+    pub home: Point,
+}
+
+/// A breakable left behind on a floor, keeps whatever damage it took
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StoredBreakable {
+    pub name: String,
+    pub home: Point,
+    #[serde(default)]
+    pub hp: Option<usize>, // older saves did not record it, those come back at full health
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -36,6 +91,8 @@ pub struct WorldTile {
     pub is_blocking: bool,
     pub is_transparent: bool,
     pub tile_type: TileType,
+    #[serde(default)]
+    pub light: Option<LightSource>, // glowing tiles light up their surroundings
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -45,6 +102,10 @@ pub enum TileType {
     DownStairs,
     UpStairs,
     Water,
+    Lava,
+    Rubble,
+    Vent,
+    Brush,
     Special,
     Unknown,
 }
@@ -56,8 +117,17 @@ impl Map {
             tiles: Vec::new(),
             rooms: Vec::new(),
             discovered: Vec::new(),
+            excavated: Vec::new(),
+            kind: FloorKind::Cave,
+            fluids: Vec::new(),
+            breakables: None,
+            ground_items: Vec::new(),
             beings: Vec::new(),
             destructibles: Vec::new(),
+            air: Vec::new(),
+            air_dirty: false,
+            light: Vec::new(),
+            light_dirty: false,
             width: 100,
             height: 70,
             depth: 0,
@@ -87,7 +157,8 @@ impl Map {
         let dest_tile = Point::new(start_pos.x + delta.x, start_pos.y + delta.y);
         if self.within_bounds(dest_tile) {
             let idx = dest_tile.to_index(self.width);
-            if !self.tiles[idx].is_blocking {
+            let breakable_in_way = matches!(self.destructibles.get(idx), Some(Some(Destructible::Entity(_))));
+            if !self.tiles[idx].is_blocking && !breakable_in_way {
                 return Some(idx);
             }
         }
@@ -108,10 +179,43 @@ impl WorldTile {
             // destructible: Destructible::Unbreakable,
             sprite: CharSprite::new('?', PURPLE, WHITESMOKE),
             tile_type: TileType::Unknown,
+            light: None,
         }
     }
 }
 
+#[cfg(test)]
+impl WorldTile {
+    /// A plain tile for tests, only walls block movement and sight
+    pub fn test(tile_type: TileType) -> Self {
+        use bracket_terminal::prelude::{BLACK, WHITE};
+        Self {
+            sprite: CharSprite::new('#', WHITE, BLACK),
+            is_blocking: tile_type == TileType::Wall,
+            is_transparent: tile_type != TileType::Wall,
+            tile_type,
+            light: None,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Map {
+    /// A small map for tests laid out row by row from `tile_types`, with every per tile layer sized to fit
+    pub fn test_strip(width: usize, height: usize, tile_types: &[TileType]) -> Self {
+        assert_eq!(tile_types.len(), width * height);
+        let mut map = Map::empty();
+        map.width = width;
+        map.height = height;
+        map.tiles = tile_types.iter().map(|t| WorldTile::test(*t)).collect();
+        map.excavated = vec![false; tile_types.len()];
+        map.fluids = vec![None; tile_types.len()];
+        map.beings = vec![None; tile_types.len()];
+        map.destructibles = vec![None; tile_types.len()];
+        map
+    }
+}
+
 impl BaseMap for Map {
     // If the block is transparent you can see through it non opaque
     fn is_opaque(&self, idx: usize) -> bool {
@@ -150,15 +254,21 @@ impl Algorithm2D for Map {
 }
 
 /// Renders the world from the player perspective
-pub fn render_map(ctx: &mut BTerm, map: &Map, config: &Config, visible: &Vec<bool>, discovered: &Vec<bool>) {
+pub fn render_map(ctx: &mut BTerm, map: &Map, config: &Config, visible: &[bool], discovered: &[bool]) {
     let mut x = 0;
     let mut y = 0;
     for tile in map.tiles.iter() {
-        if config.dev_mode {
-            ctx.set(x, y, tile.sprite.fg, tile.sprite.bg, tile.sprite.glyph);
-        } else if visible[map.xy_to_idx(x, y)] {
-            ctx.set(x, y, tile.sprite.fg, tile.sprite.bg, tile.sprite.glyph);
-        } else if discovered[map.xy_to_idx(x, y)] {
+        let idx = map.xy_to_idx(x, y);
+        if config.dev_mode || visible[idx] {
+            // tint the ceiling of weakened tunnels so the player can see the danger before digging further
+            let bg = match stability_at(map, idx) {
+                Stability::Stable => tile.sprite.bg,
+                _ => tile.sprite.bg.lerp(RGB::named(DARK_ORANGE), 0.5),
+            };
+            // dimly lit tiles are darker and take on the color of whatever is lighting them
+            let tint = RGB::from_f32(MIN_TINT, MIN_TINT, MIN_TINT) + light_at(map, idx) * (1.0 - MIN_TINT);
+            ctx.set(x, y, tile.sprite.fg * tint, bg * tint, tile.sprite.glyph);
+        } else if discovered[idx] {
             ctx.set(
                 x,
                 y,
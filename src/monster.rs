@@ -1,44 +1,251 @@
-use bracket_pathfinding::prelude::{a_star_search, DistanceAlg};
+use bracket_pathfinding::prelude::{a_star_search, DistanceAlg, Point};
 use rand::random;
 use std::cmp::max;
 
-use hecs::{Entity, With};
+use hecs::{Entity, With, World};
 
 use crate::{
     actor::{bump_tile, MoveResult, Player, Position},
+    burrow::{dig, is_diggable, BurrowMap, Burrower},
     combat::{attack, CombatStats},
+    energy::{slowed_cost, Energy, ATTACK_COST, DIG_COST, STEP_COST, WAIT_COST},
+    faction::{nearest_hostile, Target},
     fov::ViewShed,
     map::Map,
+    morgue::player_is_dead,
+    navigation::{FlowField, Navigation},
+    noise::{Noise, COMBAT_NOISE},
+    status::{inflict_on_hit, StatusEffects, StatusKind},
+    water::{can_enter, is_water, swim_cost, WadingMap, WaterAffinity},
     Message, State,
 };
 
-pub fn handle_monster_turns(state: &mut State) {
-    if let Some((_, (player_pos, player_stats))) = state
+/// How far apart pack members can be and still share what they see
+const PACK_RANGE: f32 = 10.0;
+/// How far a territorial being will stray from its home before turning back
+const TERRITORY_RADIUS: f32 = 8.0;
+/// How many turns a being searches around where it lost its target before giving up
+const SEARCH_TURNS: u32 = 10;
+
+/// Gives every being energy for the time the player's action took, then lets them act until they run out
+pub fn handle_monster_turns(state: &mut State, time: u32) {
+    for (_, energy) in state.world.query_mut::<With<&mut Energy, &Breed>>() {
+        energy.gain(time);
+    }
+
+    loop {
+        // beings take turns one action at a time so a fast being's second action comes after everyone's first
+        let ready: Vec<Entity> = state
+            .world
+            .query::<With<&Energy, &Breed>>()
+            .iter()
+            .filter(|(_, energy)| energy.ready())
+            .map(|(e, _)| e)
+            .collect();
+        if ready.is_empty() {
+            break;
+        }
+
+        for e in ready {
+            if !take_monster_turn(state, e) {
+                return;
+            }
+        }
+    }
+}
+
+/// Performs a single action for a being, returns false if the player is dead and no more turns should be taken
+fn take_monster_turn(state: &mut State, e: Entity) -> bool {
+    if player_is_dead(state) {
+        return false;
+    }
+    let target = nearest_hostile(&state.world, e);
+    let pack_sighting = pack_sighting(&state.world, e);
+    let mut attacking = None;
+
+    let attacker = match state.world.query_one::<(
+        &mut Position,
+        &mut ViewShed,
+        &mut Breed,
+        &CombatStats,
+        &mut Energy,
+        &mut Memory,
+        Option<&Burrower>,
+        Option<&StatusEffects>,
+        Option<&WaterAffinity>,
+    )>(e)
+    {
+        Ok(mut being) => match being.get() {
+            // beings slain earlier this turn are waiting to be cleaned up and can't act
+            Some((_, _, _, being_stats, energy, _, _, _, _)) if being_stats.health == 0 => {
+                energy.energy = 0;
+                return true;
+            }
+            Some((_, _, _, _, energy, _, _, Some(effects), _)) if effects.has(StatusKind::Stunned) => {
+                energy.spend(WAIT_COST);
+                return true;
+            }
+            Some((pos, view, breed, being_stats, energy, memory, burrower, effects, water)) => {
+                let from = pos.0;
+                let move_state = MoveState {
+                    me: e,
+                    pos,
+                    stats: *being_stats,
+                    view,
+                    target,
+                    attacking: &mut attacking,
+                    pack_sighting,
+                    navigation: &state.navigation,
+                    memory,
+                    burrows: burrower.is_some(),
+                    water: water.copied(),
+                    map: &mut state.map,
+                    turn_counter: state.turn_counter,
+                    message_log: &mut state.message_log,
+                };
+                let mut cost = breed.perform_move(move_state);
+                if pos.0 != from {
+                    cost = swim_cost(&state.map, pos.0.to_index(state.map.width), water.copied(), cost);
+                }
+                energy.spend(match effects {
+                    Some(effects) => slowed_cost(effects, cost),
+                    None => cost,
+                });
+                Some((*being_stats, breed.name.clone()))
+            }
+            None => None,
+        },
+        Err(..) => return true,
+    };
+
+    let Some(attacker) = attacker else {
+        // beings missing what they need to act give up their turns so everyone else isn't held up
+        if let Ok(mut energy) = state.world.get::<&mut Energy>(e) {
+            energy.energy = 0;
+        }
+        return true;
+    };
+
+    if let Some(defender) = attacking {
+        if strike(state, attacker, defender) {
+            inflict_on_hit(state, e, defender);
+        }
+    }
+    true
+}
+
+/// Resolves one being attacking another, fights out of the player's sight happen without a word in the log
+/// Returns true if the blow landed
+fn strike(state: &mut State, (attacker_stats, attacker_name): (CombatStats, String), defender: Entity) -> bool {
+    let mut query = match state
         .world
-        .query::<With<(&Position, &mut CombatStats), &Player>>()
+        .query_one::<(&mut CombatStats, &Position, Option<&Breed>, Option<&Player>)>(defender)
+    {
+        Ok(query) => query,
+        Err(..) => return false,
+    };
+    let Some((stats, pos, breed, player)) = query.get() else {
+        return false;
+    };
+
+    let name = match breed {
+        Some(breed) => breed.name.clone(),
+        None => "Player".to_string(),
+    };
+    let already_dead = stats.health == 0;
+    let health_before = stats.health;
+    let damage_stmt = attack((stats, &name), (&attacker_stats, &attacker_name));
+    let landed = stats.health < health_before;
+    state.noises.push(Noise::new(pos.0, COMBAT_NOISE));
+
+    let seen = player.is_some() || state.visible.get(pos.0.to_index(state.map.width)) == Some(&true);
+    if seen {
+        state.message_log.push(Message::new(damage_stmt, state.turn_counter));
+    }
+    if already_dead || stats.health > 0 {
+        return landed;
+    }
+    if player.is_some() {
+        if state.record.killer.is_none() {
+            state.record.killer = Some(format!("a {attacker_name}"));
+        }
+    } else if seen {
+        state.message_log.push(Message::new(
+            format!("The {attacker_name} killed the {name}"),
+            state.turn_counter,
+        ));
+    }
+    landed
+}
+
+/// Where the player is if a pack hunter or any of its nearby packmates can see them
+fn pack_sighting(world: &World, me: Entity) -> Option<Point> {
+    let player_pos = world
+        .query::<With<&Position, &Player>>()
         .iter()
         .next()
-    {
-        for (e, (pos, view, breed, being_stats)) in state
-            .world
-            .query::<(&mut Position, &mut ViewShed, &mut Breed, &CombatStats)>()
-            .iter()
+        .map(|(_, pos)| pos.0)?;
+    let (my_name, my_pos) = {
+        let mut query = world.query_one::<(&Breed, &Position)>(me).ok()?;
+        let (breed, pos) = query.get()?;
+        if !matches!(breed.ai, BeingAI::PackHunter) {
+            return None;
+        }
+        (breed.name.clone(), pos.0)
+    };
+
+    for (_, (breed, pos, view)) in world.query::<(&Breed, &Position, &ViewShed)>().iter() {
+        if breed.name == my_name
+            && DistanceAlg::Pythagoras.distance2d(my_pos, pos.0) <= PACK_RANGE
+            && view.visible_tiles.contains(&player_pos)
         {
-            let move_state = (
-                // When move_state is changed the values that were mutable are also changed
-                e,
-                pos,
-                *being_stats,
-                view,
-                player_pos.clone(),
-                &mut *player_stats,
-                &mut state.map,
-                state.turn_counter,
-                &mut state.message_log,
-            );
-            breed.perform_move(move_state);
+            return Some(player_pos);
         }
     }
+    None
+}
+
+/// Everything a being knows and can change while taking its turn
+/// When move_state is changed the values that were mutable are also changed
+pub struct MoveState<'a> {
+    pub me: Entity,
+    pub pos: &'a mut Position,
+    pub stats: CombatStats,
+    pub view: &'a mut ViewShed,
+    pub target: Option<Target>,            // the closest hostile being in sight
+    pub attacking: &'a mut Option<Entity>, // who the being attacked this turn, resolved after it acts
+    pub pack_sighting: Option<Point>,      // where the pack last spotted the player this turn
+    pub navigation: &'a Navigation,
+    pub memory: &'a mut Memory,
+    pub burrows: bool,                // whether the being can dig through walls
+    pub water: Option<WaterAffinity>, // whether the being keeps away from or to water
+    pub map: &'a mut Map,
+    pub turn_counter: usize,
+    pub message_log: &'a mut Vec<Message>,
+}
+
+/// What a being remembers about its prey after losing sight of it
+#[derive(Default)]
+pub struct Memory {
+    pub last_seen: Option<Point>,
+    pub search_turns: u32, // turns left to search once the last seen position is reached
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Refreshes the memory with a new sighting of its prey
+    pub fn saw_target(&mut self, at: Point) {
+        self.last_seen = Some(at);
+        self.search_turns = SEARCH_TURNS;
+    }
+
+    /// A noise draws the being's attention just like a glimpse of its prey would
+    pub fn heard_noise(&mut self, at: Point) {
+        self.saw_target(at);
+    }
 }
 
 /// General info about the type of monster/creature
@@ -51,13 +258,23 @@ pub struct Breed {
 
 #[derive(Clone)]
 pub enum BeingAI {
-    BasicPoke, // Simplest AI being able to wander, follow the player if they are visible, and poke the player
+    BasicPoke, // Simplest AI being able to wander, follow hostile beings it can see, and poke them
+    Cowardly,  // Fights like BasicPoke until badly hurt then runs away from whatever threatens it
+    Ambusher { revealed: bool }, // Sits still and unseen until its prey is adjacent
+    PackHunter, // Shares sightings of the player with nearby beings of the same breed
+    Territorial { home: Point }, // Only hunts near its home and goes back when its prey leaves
+    Idle,      // Stays put, for shopkeepers and others who shouldn't wander off
 }
 
 impl Breed {
-    pub fn from(name: impl ToString, species: impl ToString, ai: impl ToString) -> Self {
+    pub fn from(name: impl ToString, species: impl ToString, ai: impl ToString, spawn: Point) -> Self {
         let ai = match ai.to_string().as_str() {
             "basic" => BeingAI::BasicPoke,
+            "cowardly" => BeingAI::Cowardly,
+            "ambusher" => BeingAI::Ambusher { revealed: false },
+            "pack" => BeingAI::PackHunter,
+            "territorial" => BeingAI::Territorial { home: spawn },
+            "idle" => BeingAI::Idle,
             _ => BeingAI::BasicPoke,
         };
         Self {
@@ -67,74 +284,279 @@ impl Breed {
         }
     }
 
-    fn perform_move(
-        &mut self,
-        move_state: (
-            Entity,
-            &mut Position,
-            CombatStats,
-            &mut ViewShed,
-            Position,
-            &mut CombatStats,
-            &mut Map,
-            usize,
-            &mut Vec<Message>,
-        ),
-    ) {
+    /// Ambushers that haven't sprung their trap can't be seen
+    pub fn is_concealed(&self) -> bool {
+        matches!(self.ai, BeingAI::Ambusher { revealed: false })
+    }
+
+    /// Acts based on the being's ai, returns the time cost of what was done
+    fn perform_move(&mut self, move_state: MoveState) -> u32 {
         match self.ai {
-            BeingAI::BasicPoke => simple_ai(self, move_state),
+            BeingAI::BasicPoke => simple_ai(move_state),
+            BeingAI::Cowardly => cowardly_ai(move_state),
+            BeingAI::Ambusher { revealed } => {
+                let (cost, sprung) = ambusher_ai(self, revealed, move_state);
+                self.ai = BeingAI::Ambusher { revealed: sprung };
+                cost
+            }
+            BeingAI::PackHunter => pack_ai(move_state),
+            BeingAI::Territorial { home } => territorial_ai(home, move_state),
+            BeingAI::Idle => WAIT_COST,
         }
     }
 }
 
-fn simple_ai(
-    breed: &Breed,
-    (me, pos, attacker_stats, view, player_pos, player_stats, map, turn_counter, message_log): (
-        Entity,
-        &mut Position,
-        CombatStats,
-        &mut ViewShed,
-        Position,
-        &mut CombatStats,
-        &mut Map,
-        usize,
-        &mut Vec<Message>,
-    ),
-) {
-    let dist_to_player = DistanceAlg::Pythagoras.distance2d(player_pos.0, pos.0);
-    if dist_to_player < 1.5 {
-        let damage_stmt = attack((player_stats, &"Player"), (&attacker_stats, &breed.name));
-        message_log.push(Message::new(damage_stmt, turn_counter));
-        return;
+fn simple_ai(mut ms: MoveState) -> u32 {
+    if let Some(cost) = poke_if_adjacent(&mut ms) {
+        return cost;
+    }
+
+    if let Some(target) = ms.target {
+        ms.memory.saw_target(target.pos);
+        return chase(&mut ms, target.pos, target.is_player);
+    }
+    hunt_from_memory(&mut ms)
+}
+
+/// Closes in on prey, the player can be found with the shared field unless the being has to dig its own way
+fn chase(ms: &mut MoveState, prey: Point, is_player: bool) -> u32 {
+    if ms.burrows || !is_player {
+        return step_towards(ms, prey);
     }
-    let tile_idx = pos.0.to_index(map.width);
+    let navigation = ms.navigation;
+    follow_field(ms, &navigation.to_player);
+    STEP_COST
+}
 
-    if view.visible_tiles.contains(&player_pos.0) {
-        let path = a_star_search(tile_idx, player_pos.0.to_index(map.width), map);
-        if path.success && path.steps.len() > 1 {
-            let next_pos = map.idx_to_pos(path.steps[1]);
-            match bump_tile(map, &next_pos, pos, view, me) {
-                MoveResult::Moved(_) => return,
-                _ => {}
+/// Heads to where its prey was last seen and searches around there for a while before forgetting it
+fn hunt_from_memory(ms: &mut MoveState) -> u32 {
+    match ms.memory.last_seen {
+        Some(last_seen) if last_seen != ms.pos.0 => {
+            let before = ms.pos.0;
+            let cost = step_towards(ms, last_seen);
+            // the way there is blocked so start searching from here instead
+            if ms.pos.0 == before && cost == STEP_COST {
+                ms.memory.last_seen = Some(before);
+            }
+            cost
+        }
+        Some(_) if ms.memory.search_turns > 0 => {
+            ms.memory.search_turns -= 1;
+            wander(ms);
+            // keep searching around the new spot rather than walking back
+            ms.memory.last_seen = Some(ms.pos.0);
+            STEP_COST
+        }
+        _ => {
+            ms.memory.last_seen = None;
+            // beings at home in the water make their way back to it when there's nothing else to do
+            let navigation = ms.navigation;
+            let ashore = !is_water(ms.map, ms.pos.0.to_index(ms.map.width));
+            if ms.water == Some(WaterAffinity::Prefers) && ashore && follow_field(ms, &navigation.to_water) {
+                return STEP_COST;
             }
+            wander(ms);
+            STEP_COST
+        }
+    }
+}
+
+/// Runs from danger once it has lost over half its health, only fighting back if it can't get away
+/// Once out of sight it retreats to the stairs rather than coming back for more
+fn cowardly_ai(mut ms: MoveState) -> u32 {
+    let frightened = ms.stats.health * 2 < ms.stats.max_health;
+    if !frightened {
+        return simple_ai(ms);
+    }
+
+    let navigation = ms.navigation;
+    ms.memory.last_seen = None;
+    let threat = match ms.target {
+        Some(threat) => threat,
+        None => {
+            return match follow_field(&mut ms, &navigation.to_stairs) {
+                true => STEP_COST,
+                false => WAIT_COST,
+            };
+        }
+    };
+
+    // when other beings crowd every downhill tile, any open tile further away still helps
+    let fled = match threat.is_player {
+        true => follow_field(&mut ms, &navigation.from_player) || step_away(&mut ms, threat.pos),
+        false => step_away(&mut ms, threat.pos),
+    };
+    if fled {
+        return STEP_COST;
+    }
+    match poke_if_adjacent(&mut ms) {
+        Some(cost) => cost,
+        None => WAIT_COST,
+    }
+}
+
+/// Waits in hiding until its prey is close enough to strike, returns the cost and whether it has revealed itself
+fn ambusher_ai(breed: &Breed, revealed: bool, ms: MoveState) -> (u32, bool) {
+    if revealed {
+        return (simple_ai(ms), true);
+    }
+
+    let Some(prey) = ms
+        .target
+        .filter(|target| DistanceAlg::Pythagoras.distance2d(target.pos, ms.pos.0) < 1.5)
+    else {
+        return (WAIT_COST, false);
+    };
+    // ambushes on other beings happen unannounced
+    if prey.is_player {
+        ms.message_log.push(Message::new(
+            format!("A {} leaps out of hiding!", breed.name),
+            ms.turn_counter,
+        ));
+    }
+    (simple_ai(ms), true)
+}
+
+/// Hunts like BasicPoke but also goes after the player whenever a packmate can see them
+fn pack_ai(mut ms: MoveState) -> u32 {
+    if ms.target.is_some() {
+        return simple_ai(ms);
+    }
+
+    match ms.pack_sighting {
+        Some(sighting) => {
+            ms.memory.saw_target(sighting);
+            chase(&mut ms, sighting, true)
+        }
+        None => hunt_from_memory(&mut ms),
+    }
+}
+
+/// Only chases its prey while it is inside its territory and otherwise heads back home
+fn territorial_ai(home: Point, mut ms: MoveState) -> u32 {
+    let prey_in_territory = ms
+        .target
+        .is_some_and(|target| DistanceAlg::Pythagoras.distance2d(home, target.pos) <= TERRITORY_RADIUS);
+    if prey_in_territory {
+        return simple_ai(ms);
+    }
+
+    ms.memory.last_seen = None;
+    if DistanceAlg::Pythagoras.distance2d(home, ms.pos.0) > TERRITORY_RADIUS / 2.0 {
+        return step_towards(&mut ms, home);
+    }
+    wander(&mut ms);
+    STEP_COST
+}
+
+/// Attacks the target if it is right next to the being, returns the cost if it attacked
+fn poke_if_adjacent(ms: &mut MoveState) -> Option<u32> {
+    let target = ms.target?;
+    if DistanceAlg::Pythagoras.distance2d(target.pos, ms.pos.0) >= 1.5 {
+        return None;
+    }
+    *ms.attacking = Some(target.entity);
+    Some(ATTACK_COST)
+}
+
+/// Moves one step along the shortest path to a target, returns the time it took
+/// Burrowers path straight through diggable walls and dig them out as they go
+fn step_towards(ms: &mut MoveState, target: Point) -> u32 {
+    let start = ms.pos.0.to_index(ms.map.width);
+    let end = target.to_index(ms.map.width);
+    let path = match (ms.burrows, ms.water) {
+        (true, _) => a_star_search(start, end, &BurrowMap(ms.map)),
+        (false, Some(affinity)) => a_star_search(start, end, &WadingMap(ms.map, affinity)),
+        (false, None) => a_star_search(start, end, &*ms.map),
+    };
+    if !path.success || path.steps.len() < 2 {
+        return STEP_COST;
+    }
+
+    let next_idx = path.steps[1];
+    let mut cost = STEP_COST;
+    if ms.burrows && is_diggable(ms.map, next_idx) {
+        dig(ms.map, next_idx);
+        cost = DIG_COST;
+    }
+    let next_pos = ms.map.idx_to_pos(next_idx);
+    bump_tile(ms.map, &next_pos, ms.pos, ms.view, ms.me);
+    cost
+}
+
+/// Takes one step down a navigation field, returns true if it moved
+fn follow_field(ms: &mut MoveState, field: &FlowField) -> bool {
+    let here = ms.pos.0.to_index(ms.map.width);
+    match field.next_step(ms.map, here) {
+        Some(next_idx) if can_enter(ms.map, here, next_idx, ms.water) => {
+            let next_pos = ms.map.idx_to_pos(next_idx);
+            matches!(bump_tile(ms.map, &next_pos, ms.pos, ms.view, ms.me), MoveResult::Moved)
         }
-    } else {
-        let mut new_pos = pos.clone();
-        match random::<u8>() % 4 {
-            0 => {
-                new_pos.0.x += 1;
+        _ => false,
+    }
+}
+
+/// Moves to whichever open neighboring tile is furthest from a threat, returns true if it moved
+fn step_away(ms: &mut MoveState, threat: Point) -> bool {
+    let mut best = None;
+    let mut best_dist = DistanceAlg::Pythagoras.distance2d(ms.pos.0, threat);
+    for x in -1..=1 {
+        for y in -1..=1 {
+            let dest = Point::new(ms.pos.x() + x, ms.pos.y() + y);
+            if !ms.map.within_bounds(dest) {
+                continue;
             }
-            1 => {
-                new_pos.0.x = max(new_pos.x() - 1, 0);
+            let idx = dest.to_index(ms.map.width);
+            if ms.map.tiles[idx].is_blocking || ms.map.beings[idx].is_some() || ms.map.destructibles[idx].is_some() {
+                continue;
             }
-            2 => {
-                new_pos.0.y += 1;
+            if !can_enter(ms.map, ms.pos.0.to_index(ms.map.width), idx, ms.water) {
+                continue;
             }
-            3 => {
-                new_pos.0.y = max(new_pos.y() - 1, 0);
+            let dist = DistanceAlg::Pythagoras.distance2d(dest, threat);
+            if dist > best_dist {
+                best = Some(dest);
+                best_dist = dist;
             }
-            _ => {}
         }
-        bump_tile(map, &new_pos, pos, view, me);
     }
+
+    match best {
+        Some(dest) => matches!(
+            bump_tile(ms.map, &Position(dest), ms.pos, ms.view, ms.me),
+            MoveResult::Moved
+        ),
+        None => false,
+    }
+}
+
+/// Takes a step in a random cardinal direction, as long as the being is willing to go that way
+fn wander(ms: &mut MoveState) {
+    let mut new_pos = ms.pos.clone();
+    match random::<u8>() % 4 {
+        0 => {
+            new_pos.0.x += 1;
+        }
+        1 => {
+            new_pos.0.x = max(new_pos.x() - 1, 0);
+        }
+        2 => {
+            new_pos.0.y += 1;
+        }
+        3 => {
+            new_pos.0.y = max(new_pos.y() - 1, 0);
+        }
+        _ => {}
+    }
+    if ms.map.within_bounds(new_pos.0)
+        && !can_enter(
+            ms.map,
+            ms.pos.0.to_index(ms.map.width),
+            new_pos.0.to_index(ms.map.width),
+            ms.water,
+        )
+    {
+        return;
+    }
+    bump_tile(ms.map, &new_pos, ms.pos, ms.view, ms.me);
 }
@@ -0,0 +1,115 @@
+/*  The morgue keeps a record of the current run so that it can be summarized once the player dies.
+    A copy of the summary is written to disk and the save is removed, death is permanent.
+*/
+use std::{fs, time::SystemTime};
+
+use bracket_terminal::prelude::*;
+use hecs::With;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actor::Player,
+    combat::CombatStats,
+    item::Inventory,
+    menu::MenuIndex,
+    state::{RunState, State},
+};
+
+/// Notable events of the current run
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub kills: u32,
+    pub deepest: usize,
+    pub killer: Option<String>,
+}
+
+/// Returns true when the player has run out of health
+pub fn player_is_dead(state: &State) -> bool {
+    match state.world.query::<With<&CombatStats, &Player>>().iter().next() {
+        Some((_, stats)) => stats.health == 0,
+        None => false,
+    }
+}
+
+/// Writes the morgue file and removes the save so the dead player can't be loaded back
+pub fn bury_player(state: &mut State) {
+    if state.record.killer.is_none() {
+        state.record.killer = Some("unknown causes".to_string());
+    }
+
+    let timestamp = match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(time) => time.as_secs(),
+        Err(..) => 0,
+    };
+    let path = format!("./morgue/morgue_{timestamp}.txt");
+    match fs::create_dir_all("./morgue").and_then(|_| fs::write(&path, morgue_text(state))) {
+        Ok(..) => println!("Morgue file written to {}", path),
+        Err(err) => println!("Error while writing morgue file {}", err),
+    }
+
+    // Nothing to remove if the run was never saved
+    let _ = fs::remove_file("./saves/player.sav");
+}
+
+/// Summary of the run as it appears in the morgue file
+fn morgue_text(state: &State) -> String {
+    let mut text = String::from("Terra Incognita - Morgue File\n\n");
+    text += &format!("Killed by {} on depth {}\n", killer(state), state.map.depth);
+    text += &format!("Deepest depth reached: {}\n", state.record.deepest);
+    text += &format!("Turns survived: {}\n", state.turn_counter);
+    text += &format!("Beings slain: {}\n", state.record.kills);
+    text += &format!("World seed: {}\n", state.config.world_seed);
+
+    text += "\nInventory:\n";
+    if let Some((_, inventory)) = state.world.query::<With<&Inventory, &Player>>().iter().next() {
+        for (name, count) in inventory.counts() {
+            text += &format!("  {count}x {name}\n");
+        }
+    }
+
+    text += "\nLast messages:\n";
+    let first_msg = state.message_log.len().saturating_sub(10);
+    for msg in state.message_log[first_msg..].iter() {
+        text += &format!("  [{}] {}\n", msg.turn_sent, msg.contents);
+    }
+
+    text
+}
+
+fn killer(state: &State) -> String {
+    match &state.record.killer {
+        Some(killer) => killer.clone(),
+        None => "unknown causes".to_string(),
+    }
+}
+
+/// Shows the death screen until the player returns to the main menu
+pub fn run_game_over_systems(state: &mut State, ctx: &mut BTerm) -> RunState {
+    if let Some(VirtualKeyCode::Return | VirtualKeyCode::Escape) = ctx.key {
+        ctx.cls();
+        *state = State::new(&state.config);
+        return RunState::MainMenu(MenuIndex(0));
+    }
+
+    draw_game_over_screen(ctx, state);
+    RunState::GameOver
+}
+
+fn draw_game_over_screen(ctx: &mut BTerm, state: &State) {
+    let screen_size_x = state.config.screensize_x;
+    let screen_size_y = state.config.screensize_y;
+    let anchor_y = screen_size_y / 2 - 8;
+
+    ctx.cls();
+    ctx.draw_hollow_box(0, 0, screen_size_x - 1, screen_size_y - 1, WHITE, BLACK);
+    ctx.print_color_centered(anchor_y, RED, BLACK, "You have perished in the depths");
+    ctx.print_centered(
+        anchor_y + 3,
+        format!("Killed by {} on depth {}", killer(state), state.map.depth),
+    );
+    ctx.print_centered(anchor_y + 5, format!("Deepest depth reached: {}", state.record.deepest));
+    ctx.print_centered(anchor_y + 6, format!("Turns survived: {}", state.turn_counter));
+    ctx.print_centered(anchor_y + 7, format!("Beings slain: {}", state.record.kills));
+    ctx.print_color_centered(anchor_y + 10, GREY, BLACK, "A morgue file has been written to ./morgue");
+    ctx.print_color_centered(anchor_y + 12, LIGHT_BLUE, BLACK, "Press Enter to return to the menu");
+}
@@ -0,0 +1,155 @@
+/*  Navigation fields are shared maps of how far every tile is from something interesting.
+    They are rebuilt once per turn and any number of beings can follow them downhill for the price of looking
+    at their neighbors, rather than each running its own path search.
+*/
+use std::collections::VecDeque;
+
+use bracket_pathfinding::prelude::BaseMap;
+use hecs::With;
+
+use crate::{
+    actor::{Player, Position},
+    map::{Map, TileType},
+    State,
+};
+
+pub const UNREACHABLE: f32 = f32::MAX;
+/// Scales the distances of a field being fled from, beyond -1 fleeing beings favor open areas over dead ends
+const FLEE_FACTOR: f32 = -1.2;
+
+/// Distance of every tile from the nearest seed, following the same exits beings can walk
+pub struct FlowField {
+    values: Vec<f32>,
+}
+
+impl FlowField {
+    pub fn empty() -> Self {
+        Self { values: Vec::new() }
+    }
+
+    /// Builds the field outward from the seeds, each seed starts at its own weight
+    pub fn new(map: &Map, seeds: &[(usize, f32)]) -> Self {
+        let mut values = vec![UNREACHABLE; map.tiles.len()];
+        let mut open_list = VecDeque::new();
+        for &(idx, weight) in seeds {
+            if weight < values[idx] {
+                values[idx] = weight;
+                open_list.push_back(idx);
+            }
+        }
+
+        while let Some(idx) = open_list.pop_front() {
+            let depth = values[idx];
+            for (next_idx, cost) in map.get_available_exits(idx) {
+                if depth + cost < values[next_idx] {
+                    values[next_idx] = depth + cost;
+                    open_list.push_back(next_idx);
+                }
+            }
+        }
+
+        Self { values }
+    }
+
+    /// A field leading away from wherever `toward` leads
+    pub fn fleeing(map: &Map, toward: &FlowField) -> Self {
+        let seeds: Vec<(usize, f32)> = toward
+            .values
+            .iter()
+            .enumerate()
+            .filter(|(_, &value)| value < UNREACHABLE)
+            .map(|(idx, &value)| (idx, value * FLEE_FACTOR))
+            .collect();
+        Self::new(map, &seeds)
+    }
+
+    pub fn value(&self, idx: usize) -> f32 {
+        self.values.get(idx).copied().unwrap_or(UNREACHABLE)
+    }
+
+    /// The open neighboring tile furthest down the field, None when no neighbor is lower than the tile itself
+    pub fn next_step(&self, map: &Map, from: usize) -> Option<usize> {
+        map.get_available_exits(from)
+            .iter()
+            .map(|(idx, _)| *idx)
+            .filter(|idx| map.beings[*idx].is_none() && self.value(*idx) < self.value(from))
+            .min_by(|a, b| self.value(*a).total_cmp(&self.value(*b)))
+    }
+}
+
+/// All of the fields beings navigate by
+pub struct Navigation {
+    pub to_player: FlowField,
+    pub from_player: FlowField,
+    pub to_water: FlowField,
+    pub to_stairs: FlowField,
+}
+
+impl Navigation {
+    pub fn empty() -> Self {
+        Self {
+            to_player: FlowField::empty(),
+            from_player: FlowField::empty(),
+            to_water: FlowField::empty(),
+            to_stairs: FlowField::empty(),
+        }
+    }
+}
+
+/// Rebuilds every navigation field for the current state of the map
+pub fn update_navigation(state: &mut State) {
+    let map = &state.map;
+    let player_seed: Vec<(usize, f32)> = match state.world.query::<With<&Position, &Player>>().iter().next() {
+        Some((_, pos)) => vec![(pos.0.to_index(map.width), 0.0)],
+        None => Vec::new(),
+    };
+    let to_player = FlowField::new(map, &player_seed);
+    let from_player = FlowField::fleeing(map, &to_player);
+
+    let tiles_of = |wanted: &[TileType]| -> Vec<(usize, f32)> {
+        map.tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| wanted.contains(&tile.tile_type))
+            .map(|(idx, _)| (idx, 0.0))
+            .collect()
+    };
+    let to_water = FlowField::new(map, &tiles_of(&[TileType::Water]));
+    let to_stairs = FlowField::new(map, &tiles_of(&[TileType::UpStairs, TileType::DownStairs]));
+
+    state.navigation = Navigation {
+        to_player,
+        from_player,
+        to_water,
+        to_stairs,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 7x1 open corridor
+    fn corridor() -> Map {
+        Map::test_strip(7, 1, &[TileType::Floor; 7])
+    }
+
+    #[test]
+    fn field_leads_to_seed() {
+        let map = corridor();
+        let field = FlowField::new(&map, &[(1, 0.0)]);
+        assert_eq!(field.value(1), 0.0);
+        assert_eq!(field.value(6), 5.0);
+        assert_eq!(field.next_step(&map, 4), Some(3));
+        assert_eq!(field.next_step(&map, 1), None);
+    }
+
+    #[test]
+    fn fleeing_leads_away() {
+        let map = corridor();
+        let toward = FlowField::new(&map, &[(1, 0.0)]);
+        let away = FlowField::fleeing(&map, &toward);
+        assert_eq!(away.next_step(&map, 3), Some(4));
+        assert_eq!(away.next_step(&map, 6), None);
+    }
+}
@@ -0,0 +1,147 @@
+/*  Noise lets beings find the player without seeing them.
+    Every noise spreads out from where it was made, losing loudness with distance and a lot more through rock.
+    Beings that hear a noise go to investigate where it came from.
+*/
+use std::{cmp::Reverse, collections::BinaryHeap, collections::HashMap};
+
+use bracket_pathfinding::prelude::Point;
+use hecs::With;
+
+use crate::{
+    actor::{Player, Position},
+    map::Map,
+    monster::Memory,
+    State,
+};
+
+// How loud each action is, roughly how many open tiles away it can be heard
+pub const STEP_NOISE: u32 = 3;
+pub const COMBAT_NOISE: u32 = 8;
+pub const SMASH_NOISE: u32 = 10;
+pub const MINE_NOISE: u32 = 14;
+pub const BLAST_NOISE: u32 = 24;
+
+/// Loudness lost passing through a solid tile rather than open air
+const WALL_DAMPING: u32 = 5;
+
+/// How well a being can hear, added to the loudness of any noise reaching it
+pub struct Hearing(pub u32);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Noise {
+    pub origin: Point,
+    pub loudness: u32,
+}
+
+impl Noise {
+    pub fn new(origin: Point, loudness: u32) -> Self {
+        Self { origin, loudness }
+    }
+}
+
+/// Makes a noise where the player is standing
+pub fn player_noise(state: &mut State, loudness: u32) {
+    if let Some((_, pos)) = state.world.query::<With<&Position, &Player>>().iter().next() {
+        state.noises.push(Noise::new(pos.0, loudness));
+    }
+}
+
+/// How much loudness is lost getting from the origin to each tile, tiles past max_loss are left out
+pub fn sound_travel(map: &Map, origin: Point, max_loss: u32) -> HashMap<usize, u32> {
+    let mut loss = HashMap::new();
+    if !map.within_bounds(origin) {
+        return loss;
+    }
+
+    let mut frontier = BinaryHeap::new();
+    let start = origin.to_index(map.width);
+    loss.insert(start, 0);
+    frontier.push(Reverse((0, start)));
+
+    while let Some(Reverse((current_loss, idx))) = frontier.pop() {
+        if current_loss > loss[&idx] {
+            continue;
+        }
+        let center = map.idx_to_point(idx);
+        for x in -1..=1 {
+            for y in -1..=1 {
+                let next = Point::new(center.x + x, center.y + y);
+                if next == center || !map.within_bounds(next) {
+                    continue;
+                }
+                let next_idx = next.to_index(map.width);
+                let step = match map.tiles[next_idx].is_blocking {
+                    true => WALL_DAMPING,
+                    false => 1,
+                };
+                let next_loss = current_loss + step;
+                if next_loss > max_loss || loss.get(&next_idx).is_some_and(|&l| l <= next_loss) {
+                    continue;
+                }
+                loss.insert(next_idx, next_loss);
+                frontier.push(Reverse((next_loss, next_idx)));
+            }
+        }
+    }
+    loss
+}
+
+/// Lets every being with hearing react to the noises made this turn, then clears them
+pub fn listen_for_noises(state: &mut State) {
+    let noises: Vec<Noise> = state.noises.drain(..).collect();
+    if noises.is_empty() {
+        return;
+    }
+
+    let best_hearing = state
+        .world
+        .query::<&Hearing>()
+        .iter()
+        .map(|(_, hearing)| hearing.0)
+        .max()
+        .unwrap_or(0);
+
+    for noise in noises {
+        let loss = sound_travel(&state.map, noise.origin, noise.loudness + best_hearing);
+        for (_, (pos, hearing, memory)) in state.world.query_mut::<(&Position, &Hearing, &mut Memory)>() {
+            if let Some(&lost) = loss.get(&pos.0.to_index(state.map.width)) {
+                if lost < noise.loudness + hearing.0 {
+                    memory.heard_noise(noise.origin);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{TileType, WorldTile};
+
+    /// A 9x3 open corridor with a wall across the middle
+    fn walled_corridor() -> Map {
+        let mut map = Map::test_strip(9, 3, &[TileType::Floor; 27]);
+        for y in 0..3 {
+            let idx = map.xy_to_idx(4, y);
+            map.tiles[idx] = WorldTile::test(TileType::Wall);
+        }
+        map
+    }
+
+    #[test]
+    fn walls_muffle_noise() {
+        let map = walled_corridor();
+        let loss = sound_travel(&map, Point::new(0, 1), 20);
+        assert_eq!(loss[&map.xy_to_idx(3, 1)], 3);
+        assert_eq!(loss[&map.xy_to_idx(4, 1)], 3 + WALL_DAMPING);
+        assert_eq!(loss[&map.xy_to_idx(8, 1)], 7 + WALL_DAMPING);
+    }
+
+    #[test]
+    fn quiet_noises_fade() {
+        let map = walled_corridor();
+        let loss = sound_travel(&map, Point::new(0, 1), STEP_NOISE);
+        assert!(loss.contains_key(&map.xy_to_idx(3, 1)));
+        assert!(!loss.contains_key(&map.xy_to_idx(4, 1)));
+    }
+}
@@ -0,0 +1,209 @@
+/*  Oxygen.rs models the air of the caves and the player's need to breathe it.
+    Air thins out the deeper the player goes and grows stale in cramped tunnels, vents bring fresh air up
+    from somewhere below. Breathing bad air drains the player's oxygen and once it is gone they suffocate.
+*/
+use bracket_pathfinding::prelude::{DistanceAlg, Point};
+use hecs::With;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actor::{Player, Position},
+    combat::CombatStats,
+    item::Inventory,
+    map::{Map, TileType},
+    messagelog::Message,
+    morgue::player_is_dead,
+    water::is_water,
+    State,
+};
+
+/// Breath the player can hold
+pub const PLAYER_OXYGEN: u32 = 100;
+/// Air quality of a well ventilated tile at the surface
+pub const FRESH_AIR: u32 = 100;
+/// Air quality lost every floor down
+const AIR_LOSS_PER_DEPTH: u32 = 6;
+/// No matter how deep the caves go there is always a little air
+const THINNEST_AIR: u32 = 20;
+/// How far around a tile is looked at to decide how enclosed it is
+const OPENNESS_RADIUS: i32 = 3;
+/// Tiles with fewer open tiles than this around them are stuffy
+const ENCLOSED_OPENNESS: u32 = 24;
+/// Air quality lost for each open tile short of ENCLOSED_OPENNESS
+const STUFFINESS: u32 = 2;
+/// How far the fresh air from a vent spreads
+const VENT_RADIUS: f32 = 6.0;
+/// Air quality lost per tile away from a vent
+const VENT_FALLOFF: f32 = 5.0;
+/// Air at or above this quality refills oxygen, below it oxygen is used up
+pub const BREATHABLE: u32 = 60;
+/// Oxygen used up each turn spent holding breath underwater
+const UNDERWATER_BREATH: i32 = 8;
+/// Health lost each turn spent without any oxygen
+const SUFFOCATION_DAMAGE: u32 = 2;
+/// Oxygen restored by breathing from a canister
+const CANISTER_OXYGEN: u32 = 60;
+pub const CANISTER: &str = "Oxygen Canister";
+
+/// How much breath a being can hold
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Oxygen {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl Oxygen {
+    pub fn new(max: u32) -> Self {
+        Self { current: max, max }
+    }
+}
+
+/// The best air to be found on a floor of this depth
+pub fn depth_air(depth: usize) -> u32 {
+    FRESH_AIR
+        .saturating_sub(depth as u32 * AIR_LOSS_PER_DEPTH)
+        .max(THINNEST_AIR)
+}
+
+/// Air quality of a tile, maps from before air was tracked are treated as well ventilated for their depth
+pub fn air_at(map: &Map, idx: usize) -> u32 {
+    map.air.get(idx).copied().unwrap_or_else(|| depth_air(map.depth))
+}
+
+/// Recalculates the air of every tile, the map is marked air_dirty whenever the shape of the cave changes
+pub fn settle_air(map: &mut Map) {
+    let base = depth_air(map.depth);
+    let mut air: Vec<u32> = (0..map.tiles.len())
+        .map(|idx| {
+            let stuffy = ENCLOSED_OPENNESS.saturating_sub(openness(map, idx)) * STUFFINESS;
+            base.saturating_sub(stuffy).max(THINNEST_AIR)
+        })
+        .collect();
+
+    let vents: Vec<Point> = (0..map.tiles.len())
+        .filter(|&idx| map.tiles[idx].tile_type == TileType::Vent)
+        .map(|idx| map.idx_to_point(idx))
+        .collect();
+    for vent in vents {
+        let reach = VENT_RADIUS as i32;
+        for x in (vent.x - reach)..=(vent.x + reach) {
+            for y in (vent.y - reach)..=(vent.y + reach) {
+                let pt = Point::new(x, y);
+                let dist = DistanceAlg::Pythagoras.distance2d(vent, pt);
+                if !map.within_bounds(pt) || dist > VENT_RADIUS {
+                    continue;
+                }
+                let idx = pt.to_index(map.width);
+                let fresh = (FRESH_AIR as f32 - dist * VENT_FALLOFF) as u32;
+                air[idx] = air[idx].max(fresh);
+            }
+        }
+    }
+
+    map.air = air;
+    map.air_dirty = false;
+}
+
+/// Counts the open tiles around an index, the more there are the more air there is to go around
+fn openness(map: &Map, idx: usize) -> u32 {
+    let center = map.idx_to_point(idx);
+    let mut open = 0;
+    for x in (center.x - OPENNESS_RADIUS)..=(center.x + OPENNESS_RADIUS) {
+        for y in (center.y - OPENNESS_RADIUS)..=(center.y + OPENNESS_RADIUS) {
+            let pt = Point::new(x, y);
+            if pt != center && map.within_bounds(pt) && !map.tiles[pt.to_index(map.width)].is_blocking {
+                open += 1;
+            }
+        }
+    }
+    open
+}
+
+/// How much oxygen changes in a turn spent breathing air of a given quality
+pub fn breath(air: u32) -> i32 {
+    match air >= BREATHABLE {
+        true => ((air - BREATHABLE) / 10 + 1) as i32,
+        false => -(((BREATHABLE - air) / 15 + 1) as i32),
+    }
+}
+
+/// The player breathes the air of the tile they are standing on
+/// When they run out they breathe from a canister if they have one, otherwise they start to suffocate
+/// There is no air to be had underwater, a swimming player holds their breath until they drown
+pub fn breathe(state: &mut State) {
+    // maps loaded from a save have no air until it is settled again
+    if state.map.air_dirty || state.map.air.len() != state.map.tiles.len() {
+        settle_air(&mut state.map);
+    }
+
+    let turn = state.turn_counter;
+    let map = &state.map;
+    let mut underwater = false;
+    if let Some((_, (pos, oxygen, stats, inventory))) = state
+        .world
+        .query_mut::<With<(&Position, &mut Oxygen, &mut CombatStats, &mut Inventory), &Player>>()
+        .into_iter()
+        .next()
+    {
+        let idx = pos.0.to_index(map.width);
+        underwater = is_water(map, idx);
+        let change = match underwater {
+            true => -UNDERWATER_BREATH,
+            false => breath(air_at(map, idx)),
+        };
+        let was_low = oxygen.current <= oxygen.max / 4;
+        oxygen.current = (oxygen.current as i32 + change).clamp(0, oxygen.max as i32) as u32;
+        if !was_low && oxygen.current <= oxygen.max / 4 {
+            state
+                .message_log
+                .push(Message::new("You are running short of breath".to_string(), turn));
+        }
+        if oxygen.current > 0 {
+            return;
+        }
+
+        if let Some(idx) = inventory.items.iter().position(|item| item.name == CANISTER) {
+            inventory.items.remove(idx);
+            oxygen.current = CANISTER_OXYGEN.min(oxygen.max);
+            state
+                .message_log
+                .push(Message::new(format!("You gasp from an {CANISTER}"), turn));
+            return;
+        }
+
+        stats.health = stats.health.saturating_sub(SUFFOCATION_DAMAGE);
+        let plight = match underwater {
+            true => "You are drowning!",
+            false => "You are suffocating!",
+        };
+        state.message_log.push(Message::new(plight.to_string(), turn));
+    }
+
+    if player_is_dead(state) && state.record.killer.is_none() {
+        let killer = match underwater {
+            true => "drowning",
+            false => "suffocation",
+        };
+        state.record.killer = Some(killer.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deeper_air_is_thinner() {
+        assert_eq!(depth_air(0), FRESH_AIR);
+        assert!(depth_air(5) < depth_air(2));
+        assert_eq!(depth_air(1000), THINNEST_AIR);
+    }
+
+    #[test]
+    fn bad_air_drains_oxygen() {
+        assert!(breath(FRESH_AIR) > 0);
+        assert!(breath(BREATHABLE) > 0);
+        assert!(breath(BREATHABLE - 1) < 0);
+        assert!(breath(THINNEST_AIR) < breath(BREATHABLE - 1));
+    }
+}
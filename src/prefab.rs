@@ -4,18 +4,21 @@
    3. In worldgen, prefabs may be used by the methods
 */
 
+use std::fs;
+
 use crate::{
     actor::CharSprite,
     data_read::named_tile,
     map::{TileType, WorldTile},
 };
-use bracket_terminal::prelude::{XpFile, BLACK};
+use bracket_terminal::prelude::{Point, XpFile, BLACK};
 
 #[derive(Default)]
 pub struct Prefab {
     pub structure: Vec<WorldTile>,
     pub width: usize,
     pub height: usize,
+    pub markers: Vec<(Point, char)>, // special spots within the prefab such as where someone stands
 }
 
 pub fn xy_to_idx(x: usize, y: usize, width: usize) -> usize {
@@ -49,6 +52,7 @@ pub fn load_rex_room(rex_file: impl ToString) -> Prefab {
                         is_blocking: true,
                         is_transparent: false,
                         tile_type: TileType::Special,
+                        light: None,
                     },
                     _ => {
                         println!("{}, {} didn't match {}", x, y, (cell.ch as u8) as char);
@@ -57,6 +61,7 @@ pub fn load_rex_room(rex_file: impl ToString) -> Prefab {
                             is_blocking: true,
                             is_transparent: false,
                             tile_type: TileType::Special,
+                            light: None,
                         }
                     }
                 }
@@ -67,6 +72,40 @@ pub fn load_rex_room(rex_file: impl ToString) -> Prefab {
     prefab
 }
 
+/// Loads a plain text prefab from the prefabs folder by it's name without extension
+/// Letters mark spots of interest and are left as floor, see `markers`, stars are torches set in the walls
+pub fn load_text_prefab(name: impl ToString) -> Prefab {
+    let file_name = format!("resources/prefabs/{}.txt", name.to_string());
+    let contents = fs::read_to_string(&file_name).expect("Unable to read prefab, please check file.");
+    let lines: Vec<&str> = contents.lines().collect();
+
+    let mut prefab = Prefab {
+        width: lines.iter().map(|line| line.chars().count()).max().unwrap_or(0),
+        height: lines.len(),
+        ..Default::default()
+    };
+    prefab.structure = vec![named_tile("Plank Floor"); prefab.width * prefab.height];
+
+    for (y, line) in lines.iter().enumerate() {
+        for (x, ch) in line.chars().enumerate() {
+            let idx = xy_to_idx(x, y, prefab.width);
+            prefab.structure[idx] = match ch {
+                '#' => named_tile("Timber Wall"),
+                '+' => named_tile("Open Door"),
+                '*' => named_tile("Wall Torch"),
+                '.' => named_tile("Plank Floor"),
+                marker if marker.is_ascii_alphabetic() => {
+                    prefab.markers.push((Point::new(x, y), marker));
+                    named_tile("Plank Floor")
+                }
+                _ => named_tile("Plank Floor"),
+            };
+        }
+    }
+
+    prefab
+}
+
 // /// Clockwise rotation
 // pub enum Rotation {
 //     Ninety,
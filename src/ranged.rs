@@ -0,0 +1,297 @@
+/*  Ranged combat lets the player hit beings from afar by throwing or shooting something at them.
+    A target is picked by moving a cursor over what the player can see, the shot flies along a straight line
+    and hits the first being in its way, a wall stops it short. Whatever was fired lands where it stopped.
+*/
+use bracket_pathfinding::prelude::{BaseMap, DistanceAlg, Point};
+use bracket_terminal::prelude::*;
+use hecs::{Entity, With};
+
+use crate::{
+    actor::{Player, Position},
+    combat::{attack, CombatStats},
+    data_read::{item_effects, item_ranged, named_item_builder, RangedData, ENTITY_DB},
+    effects::apply_effects,
+    energy::ATTACK_COST,
+    experience::credit_player_kill,
+    faction::visible_hostiles,
+    item::Inventory,
+    map::Map,
+    messagelog::Message,
+    monster::Breed,
+    noise::{Noise, COMBAT_NOISE},
+    state::{PlayerResponse, RunState, State},
+};
+
+/// What the player is aiming and where
+#[derive(Clone)]
+pub struct Targeting {
+    pub weapon: String,
+    pub cursor: Point,
+}
+
+/// Where a shot ends up after flying along its line
+#[derive(Debug, PartialEq)]
+pub enum ShotEnd {
+    Hit(Entity, Point), // struck a being
+    Landed(Point),      // fell to the ground without hitting anything
+}
+
+impl ShotEnd {
+    pub fn point(&self) -> Point {
+        match self {
+            ShotEnd::Hit(_, pt) | ShotEnd::Landed(pt) => *pt,
+        }
+    }
+}
+
+/// The first weapon the player carries that can be fired right now, launchers need their ammo on hand
+/// Thrown consumables like bombs are left to be picked out from the use menu
+fn ready_weapon(inventory: &Inventory) -> Option<(String, RangedData)> {
+    inventory.items.iter().find_map(|item| {
+        if item_effects(&item.name).is_some() {
+            return None;
+        }
+        let ranged = item_ranged(&item.name)?;
+        let loaded = match &ranged.ammo {
+            Some(ammo) => inventory.items.iter().any(|other| other.name == *ammo),
+            None => true,
+        };
+        loaded.then(|| (item.name.clone(), ranged))
+    })
+}
+
+/// Readies a ranged weapon and aims at the nearest hostile, None if the player has nothing to fire
+pub fn start_targeting(state: &mut State) -> Option<RunState> {
+    let (player_pos, weapon) = match state
+        .world
+        .query::<With<(&Position, &Inventory), &Player>>()
+        .iter()
+        .next()
+    {
+        Some((_, (pos, inventory))) => (pos.0, ready_weapon(inventory)),
+        None => return None,
+    };
+    match weapon {
+        Some((weapon, _)) => Some(aim(state, weapon, player_pos)),
+        None => {
+            state
+                .message_log
+                .push(Message::new("You have nothing to fire".to_string(), state.turn_counter));
+            None
+        }
+    }
+}
+
+/// Aims something at the nearest hostile, or at the player's feet when there is nothing to aim at
+pub fn aim(state: &State, weapon: String, player_pos: Point) -> RunState {
+    let cursor = match visible_hostiles(state).first() {
+        Some((_, pos, _)) => *pos,
+        None => player_pos,
+    };
+    RunState::Targeting(Targeting { weapon, cursor })
+}
+
+/// Moves the cursor around until the player fires or gives up, firing takes the player's turn
+pub fn run_targeting_systems(state: &mut State, ctx: &mut BTerm, mut targeting: Targeting) -> PlayerResponse {
+    let (Some(player_pos), Some(ranged)) = (player_position(state), item_ranged(&targeting.weapon)) else {
+        return PlayerResponse::StateChange(RunState::InGame);
+    };
+
+    if let Some(key) = ctx.key {
+        let delta = match key {
+            VirtualKeyCode::Up | VirtualKeyCode::K => Point::new(0, -1),
+            VirtualKeyCode::Down | VirtualKeyCode::J => Point::new(0, 1),
+            VirtualKeyCode::Left | VirtualKeyCode::H => Point::new(-1, 0),
+            VirtualKeyCode::Right | VirtualKeyCode::L => Point::new(1, 0),
+            VirtualKeyCode::Y => Point::new(-1, -1),
+            VirtualKeyCode::U => Point::new(1, -1),
+            VirtualKeyCode::N => Point::new(-1, 1),
+            VirtualKeyCode::M => Point::new(1, 1),
+            VirtualKeyCode::Tab => {
+                targeting.cursor = next_target(state, targeting.cursor);
+                Point::zero()
+            }
+            VirtualKeyCode::Escape => return PlayerResponse::StateChange(RunState::InGame),
+            VirtualKeyCode::Return | VirtualKeyCode::F => {
+                if out_of_range(player_pos, targeting.cursor, &ranged) || targeting.cursor == player_pos {
+                    state
+                        .message_log
+                        .push(Message::new("That is out of range".to_string(), state.turn_counter));
+                    return PlayerResponse::StateChange(RunState::Targeting(targeting));
+                }
+                fire(state, &targeting, &ranged, player_pos);
+                return PlayerResponse::TurnAdvance(ATTACK_COST);
+            }
+            _ => Point::zero(),
+        };
+
+        // the cursor only moves over what the player can see
+        let moved = targeting.cursor + delta;
+        if state.map.within_bounds(moved) && state.visible[moved.to_index(state.map.width)] {
+            targeting.cursor = moved;
+        }
+    }
+
+    draw_targeting(ctx, state, &targeting, &ranged, player_pos);
+    PlayerResponse::StateChange(RunState::Targeting(targeting))
+}
+
+fn player_position(state: &State) -> Option<Point> {
+    state
+        .world
+        .query::<With<&Position, &Player>>()
+        .iter()
+        .next()
+        .map(|(_, pos)| pos.0)
+}
+
+fn out_of_range(from: Point, to: Point, ranged: &RangedData) -> bool {
+    DistanceAlg::Pythagoras.distance2d(from, to) > ranged.range as f32
+}
+
+/// The next visible hostile after the one under the cursor, wrapping back around to the nearest
+fn next_target(state: &State, cursor: Point) -> Point {
+    let hostiles = visible_hostiles(state);
+    let current = hostiles.iter().position(|(_, pos, _)| *pos == cursor);
+    let next = match current {
+        Some(idx) => (idx + 1) % hostiles.len(),
+        None => 0,
+    };
+    hostiles.get(next).map(|(_, pos, _)| *pos).unwrap_or(cursor)
+}
+
+/// The tiles a shot flies over from one point toward another, not including where it started
+/// The line stops short of anything opaque in the way
+pub fn line_of_fire(map: &Map, from: Point, to: Point) -> Vec<Point> {
+    let mut line = Vec::new();
+    for pt in Bresenham::new(from, to).skip(1).chain(std::iter::once(to)) {
+        if !map.within_bounds(pt) || map.is_opaque(pt.to_index(map.width)) {
+            break;
+        }
+        line.push(pt);
+    }
+    line
+}
+
+/// Follows the line of fire until the shot hits a being or runs out of line
+pub fn trace_shot(map: &Map, from: Point, to: Point) -> ShotEnd {
+    let line = line_of_fire(map, from, to);
+    for pt in line.iter() {
+        if let Some(being) = map.beings[pt.to_index(map.width)] {
+            return ShotEnd::Hit(being, *pt);
+        }
+    }
+    ShotEnd::Landed(line.last().copied().unwrap_or(from))
+}
+
+/// Fires the weapon at the cursor, the thrown weapon or the ammo is used up and left where the shot ended
+fn fire(state: &mut State, targeting: &Targeting, ranged: &RangedData, player_pos: Point) {
+    let turn = state.turn_counter;
+    let projectile = ranged.ammo.clone().unwrap_or_else(|| targeting.weapon.clone());
+    let attacker_stats = match state
+        .world
+        .query_mut::<With<(&CombatStats, &mut Inventory), &Player>>()
+        .into_iter()
+        .next()
+    {
+        Some((_, (stats, inventory))) => {
+            if let Some(idx) = inventory.items.iter().position(|item| item.name == projectile) {
+                inventory.items.remove(idx);
+            }
+            CombatStats {
+                strength: ranged.power,
+                ..*stats
+            }
+        }
+        None => return,
+    };
+
+    let end = trace_shot(&state.map, player_pos, targeting.cursor);
+    // anything with effects bursts where it ends up rather than being left behind
+    if let Some(effects) = item_effects(&projectile) {
+        apply_effects(state, &effects, end.point());
+        return;
+    }
+
+    let landing = match end {
+        ShotEnd::Hit(target, pt) => {
+            let slain = match state.world.query_one_mut::<(&mut CombatStats, &Breed)>(target) {
+                Ok((stats, breed)) => {
+                    let already_dead = stats.health == 0;
+                    let damage_stmt = attack((stats, &breed.name), (&attacker_stats, &"Player"));
+                    state.message_log.push(Message::new(damage_stmt, turn));
+                    !already_dead && stats.health == 0
+                }
+                Err(..) => false,
+            };
+            if slain {
+                credit_player_kill(state, target);
+            }
+            pt
+        }
+        ShotEnd::Landed(pt) => {
+            state
+                .message_log
+                .push(Message::new(format!("The {projectile} clatters to the ground"), turn));
+            pt
+        }
+    };
+    state.noises.push(Noise::new(landing, COMBAT_NOISE));
+
+    let builder = named_item_builder(&ENTITY_DB.lock().unwrap(), &projectile, Position(landing));
+    if let Some(mut eb) = builder {
+        state.world.spawn(eb.build());
+    }
+}
+
+/// Highlights the path of the shot and the tile under the cursor
+fn draw_targeting(ctx: &mut BTerm, state: &State, targeting: &Targeting, ranged: &RangedData, player_pos: Point) {
+    let color = match out_of_range(player_pos, targeting.cursor, ranged) {
+        true => RED,
+        false => YELLOW,
+    };
+    for pt in line_of_fire(&state.map, player_pos, targeting.cursor) {
+        if pt != targeting.cursor {
+            ctx.set_bg(pt.x, pt.y, DARK_GRAY);
+        }
+    }
+    ctx.set_bg(targeting.cursor.x, targeting.cursor.y, color);
+
+    let top = state.config.screensize_y - 10;
+    ctx.print_color(
+        2,
+        top,
+        YELLOW,
+        BLACK,
+        format!(" Aiming {} (range {}) ", targeting.weapon, ranged.range),
+    );
+    ctx.print_color(2, top + 9, GREY, BLACK, " Enter fire  Tab next target  Esc cancel ");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::TileType;
+
+    /// A 9x1 corridor with a wall at x = 6
+    fn corridor() -> Map {
+        let mut tiles = [TileType::Floor; 9];
+        tiles[6] = TileType::Wall;
+        Map::test_strip(9, 1, &tiles)
+    }
+
+    #[test]
+    fn walls_stop_the_line() {
+        let map = corridor();
+        let line = line_of_fire(&map, Point::new(0, 0), Point::new(8, 0));
+        assert_eq!(line.last(), Some(&Point::new(5, 0)));
+        assert_eq!(line.len(), 5);
+    }
+
+    #[test]
+    fn shots_land_short_of_walls() {
+        let map = corridor();
+        let end = trace_shot(&map, Point::new(0, 0), Point::new(8, 0));
+        assert_eq!(end, ShotEnd::Landed(Point::new(5, 0)));
+    }
+}
@@ -0,0 +1,129 @@
+/*  Rest.rs lets the player recover their health.
+    Wounds slowly heal on their own as long as the player is fed, resting passes turns until they are
+    healed or something interrupts them.
+*/
+use bracket_terminal::prelude::BTerm;
+use hecs::With;
+
+use crate::{
+    actor::Player,
+    combat::CombatStats,
+    faction::visible_hostiles,
+    hunger::{player_hunger, HungerState},
+    messagelog::Message,
+    oxygen::Oxygen,
+    state::RunState,
+    water::player_in_water,
+    State,
+};
+
+/// Turns between each bit of healing
+const REGEN_INTERVAL: usize = 5;
+/// Portion of max health healed each time, out of 100
+const REGEN_PERCENT: u32 = 1;
+
+/// How much health the player heals every REGEN_INTERVAL turns
+pub fn regen_amount(max_health: u32) -> u32 {
+    (max_health * REGEN_PERCENT / 100).max(1)
+}
+
+/// Heals the player a little every few turns, a weak or starving body can't mend itself
+pub fn regenerate(state: &mut State) {
+    if !state.turn_counter.is_multiple_of(REGEN_INTERVAL) {
+        return;
+    }
+    if player_hunger(&state.world).is_some_and(|hunger| hunger >= HungerState::Weak) {
+        return;
+    }
+    if let Some((_, stats)) = state
+        .world
+        .query_mut::<With<&mut CombatStats, &Player>>()
+        .into_iter()
+        .next()
+    {
+        if stats.health > 0 {
+            stats.health = (stats.health + regen_amount(stats.max_health)).min(stats.max_health);
+        }
+    }
+}
+
+/// Starts resting unless something stops the player from doing so right away
+pub fn start_resting(state: &mut State) -> Option<RunState> {
+    let health = player_health(state)?;
+    match rest_interruption(state, health) {
+        Some(reason) => {
+            state
+                .message_log
+                .push(Message::new(format!("You can't rest, {reason}"), state.turn_counter));
+            None
+        }
+        None => {
+            state
+                .message_log
+                .push(Message::new("You sit down to rest".to_string(), state.turn_counter));
+            Some(RunState::Resting(health))
+        }
+    }
+}
+
+/// Waits out a turn at a time until healed or interrupted, `last_health` is the health after the previous turn
+/// Returns None to keep resting and the reason to stop otherwise
+pub fn rest_turn(state: &mut State, ctx: &BTerm, last_health: u32) -> Option<String> {
+    if ctx.key.is_some() {
+        return Some("You stop resting".to_string());
+    }
+    let Some(health) = player_health(state) else {
+        return Some("You stop resting".to_string());
+    };
+    if let Some(reason) = rest_interruption(state, last_health) {
+        return Some(format!("You stop resting, {reason}"));
+    }
+    if let Some((_, stats)) = state.world.query::<With<&CombatStats, &Player>>().iter().next() {
+        if health >= stats.max_health {
+            return Some("You feel rested".to_string());
+        }
+    }
+    None
+}
+
+/// Why the player can't keep resting, if there is a reason
+fn rest_interruption(state: &State, last_health: u32) -> Option<String> {
+    if player_in_water(state) {
+        return Some("you are swimming".to_string());
+    }
+    if let Some((_, _, name)) = visible_hostiles(state).first() {
+        return Some(format!("a {name} is in view"));
+    }
+    if player_health(state).is_some_and(|health| health < last_health) {
+        return Some("something is hurting you".to_string());
+    }
+    if player_hunger(&state.world).is_some_and(|hunger| hunger >= HungerState::Weak) {
+        return Some("you are too hungry".to_string());
+    }
+    if let Some((_, oxygen)) = state.world.query::<With<&Oxygen, &Player>>().iter().next() {
+        if oxygen.current <= oxygen.max / 4 {
+            return Some("you are short of breath".to_string());
+        }
+    }
+    None
+}
+
+pub fn player_health(state: &State) -> Option<u32> {
+    state
+        .world
+        .query::<With<&CombatStats, &Player>>()
+        .iter()
+        .next()
+        .map(|(_, stats)| stats.health)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn always_heals_something() {
+        assert_eq!(regen_amount(10), 1);
+        assert_eq!(regen_amount(200), 2);
+    }
+}
@@ -1,14 +1,25 @@
+use hecs::{EntityBuilder, World};
 use serde::{Deserialize, Serialize};
 use std::fs;
 
 use crate::{
     actor::{Player, Position},
     add_player_to_room,
+    combat::{seed_combat_rng, CombatStats},
     config::Config,
+    equipment::{equip_from_pack, stats_without_gear, Equipment},
+    experience::Experience,
     furnish_map,
+    hunger::Hunger,
+    item::{Inventory, Item},
+    lighting::Torch,
     map::Map,
+    morgue::RunRecord,
+    oxygen::Oxygen,
+    shop::Purse,
     state::State,
-    worldgen::cull_destructibles,
+    status::StatusEffects,
+    worldgen::{cull_destructibles, store_furnishings},
 };
 
 #[derive(Deserialize, Serialize)]
@@ -18,6 +29,102 @@ struct GameData {
     last_depth: usize,
     last_pos: Position,
     seed: u64,
+    #[serde(default)]
+    turns: usize,
+    #[serde(default)]
+    record: RunRecord,
+    #[serde(default)]
+    player: PlayerSnapshot,
+}
+
+/// The parts of the player that change over a run, whatever an older save is missing keeps its new game value
+#[derive(Default, Deserialize, Serialize)]
+struct PlayerSnapshot {
+    stats: Option<CombatStats>, // kept with the experience so level up gains survive, less any gear bonuses
+    #[serde(default)]
+    equipped: Vec<String>, // put back on through the pack so their bonuses are applied again
+    experience: Option<Experience>,
+    purse: Option<Purse>,
+    inventory: Option<Inventory>,
+    oxygen: Option<Oxygen>,
+    hunger: Option<Hunger>,
+    torch: Option<Torch>,
+    status_effects: Option<StatusEffects>,
+}
+
+impl PlayerSnapshot {
+    /// Copies the player's components out of the world
+    fn take(world: &World) -> Self {
+        let Some((player, _)) = world.query::<&Player>().iter().next() else {
+            return Self::default();
+        };
+        let equipment = world.get::<&Equipment>(player).ok();
+        let stats = world.get::<&CombatStats>(player).ok().map(|stats| match &equipment {
+            Some(equipment) => stats_without_gear(equipment, &stats),
+            None => *stats,
+        });
+        let equipped = equipment.map_or(Vec::new(), |equipment| {
+            equipment
+                .hand
+                .iter()
+                .chain(equipment.body.iter())
+                .map(|item| item.name.clone())
+                .collect()
+        });
+        Self {
+            stats,
+            equipped,
+            experience: world.get::<&Experience>(player).ok().map(|c| *c),
+            purse: world.get::<&Purse>(player).ok().map(|c| *c),
+            inventory: world.get::<&Inventory>(player).ok().map(|c| (*c).clone()),
+            oxygen: world.get::<&Oxygen>(player).ok().map(|c| (*c).clone()),
+            hunger: world.get::<&Hunger>(player).ok().map(|c| (*c).clone()),
+            torch: world.get::<&Torch>(player).ok().map(|c| (*c).clone()),
+            status_effects: world.get::<&StatusEffects>(player).ok().map(|c| (*c).clone()),
+        }
+    }
+
+    /// Puts the saved components back on the player, replacing the ones a new player starts with
+    fn restore(self, world: &mut World) {
+        let Some((player, _)) = world.query::<&Player>().iter().next() else {
+            return;
+        };
+        let mut eb = EntityBuilder::new();
+        if let Some(stats) = self.stats {
+            eb.add(stats);
+        }
+        if let Some(experience) = self.experience {
+            eb.add(experience);
+        }
+        if let Some(purse) = self.purse {
+            eb.add(purse);
+        }
+        if let Some(inventory) = self.inventory {
+            eb.add(inventory);
+        }
+        if let Some(oxygen) = self.oxygen {
+            eb.add(oxygen);
+        }
+        if let Some(hunger) = self.hunger {
+            eb.add(hunger);
+        }
+        if let Some(torch) = self.torch {
+            eb.add(torch);
+        }
+        if let Some(status_effects) = self.status_effects {
+            eb.add(status_effects);
+        }
+        if let Err(e) = world.insert(player, eb.build()) {
+            println!("{}", e);
+        }
+
+        for name in self.equipped {
+            if let Ok(mut inventory) = world.get::<&mut Inventory>(player) {
+                inventory.items.push(Item::new(&name));
+            }
+            equip_from_pack(world, &name);
+        }
+    }
 }
 
 impl GameData {
@@ -28,6 +135,9 @@ impl GameData {
             last_depth: 0,
             last_pos: Position::new(0, 0),
             seed: 0,
+            turns: 0,
+            record: RunRecord::default(),
+            player: PlayerSnapshot::default(),
         }
     }
 }
@@ -52,12 +162,17 @@ pub fn save_game(state: &mut State) {
 /// Generates the save data from state for serialization
 fn generate(state: &mut State) -> GameData {
     // make sure the most recent generated map is updated
-    state.generated_maps.insert(state.map.depth, state.map.clone());
+    let mut current_map = state.map.clone();
+    store_furnishings(&state.world, &mut current_map);
+    state.generated_maps.insert(state.map.depth, current_map);
 
     let mut data = GameData::new();
 
     data.seed = state.config.world_seed;
     data.last_depth = state.map.depth;
+    data.turns = state.turn_counter;
+    data.record = state.record.clone();
+    data.player = PlayerSnapshot::take(&state.world);
 
     // Collect all the maps the player has visited as it may have destroyed terrain so we couldn't regenerate it
     for (depth, map) in state.generated_maps.iter() {
@@ -79,6 +194,10 @@ pub fn start_load_game(config: Config) -> State {
     let height = 70;
 
     let mut load_state = State::new(&config);
+    // offset by the turn so reloading doesn't replay the same rolls from the start of the run
+    seed_combat_rng(load_data.seed.wrapping_add(load_data.turns as u64));
+    load_state.turn_counter = load_data.turns;
+    load_state.record = load_data.record;
     for (map, depth) in load_data.maps.iter().zip(load_data.depths.iter()) {
         load_state.generated_maps.insert(*depth, map.clone());
     }
@@ -86,6 +205,10 @@ pub fn start_load_game(config: Config) -> State {
     for (_, map) in load_state.generated_maps.iter_mut() {
         map.beings = vec![None; width * height];
         map.destructibles = vec![None; width * height];
+        if map.excavated.len() != width * height {
+            // saves from before tunnels were tracked have no excavation data
+            map.excavated = vec![false; width * height];
+        }
     }
 
     load_state.map = match load_state.generated_maps.get(&load_data.last_depth) {
@@ -93,14 +216,15 @@ pub fn start_load_game(config: Config) -> State {
         None => panic!("Map could not be found for the last recorded depth"),
     };
 
-    generate_content(&mut load_state, load_data.last_pos);
+    generate_content(&mut load_state, load_data.last_pos, load_data.player);
 
     load_state
 }
 
-/// Loads data that is algorithmically creatable
-fn generate_content(state: &mut State, player_pos: Position) {
+/// Loads data that is algorithmically creatable and puts the player back the way they were saved
+fn generate_content(state: &mut State, player_pos: Position, player: PlayerSnapshot) {
     add_player_to_room(&mut state.world, player_pos);
+    player.restore(&mut state.world);
     furnish_map(&mut state.world, &mut state.map);
     cull_destructibles(&mut state.map);
 }
@@ -120,3 +244,28 @@ fn load_state_from_save() -> GameData {
 
     game_data
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::Item;
+
+    #[test]
+    fn player_snapshot_survives_a_save() {
+        let mut world = World::new();
+        let mut inventory = Inventory::new();
+        inventory.items.push(Item::new("Stone"));
+        let mut hunger = Hunger::new(1000);
+        hunger.satiation = 400;
+        world.spawn((Player, inventory, hunger));
+
+        let json = serde_json::to_string(&PlayerSnapshot::take(&world)).unwrap();
+        let snapshot: PlayerSnapshot = serde_json::from_str(&json).unwrap();
+
+        let mut loaded = World::new();
+        let player = loaded.spawn((Player, Inventory::new(), Hunger::new(1000)));
+        snapshot.restore(&mut loaded);
+        assert_eq!(loaded.get::<&Inventory>(player).unwrap().items[0].name, "Stone");
+        assert_eq!(loaded.get::<&Hunger>(player).unwrap().satiation, 400);
+    }
+}
@@ -0,0 +1,181 @@
+/*  Shops let the player trade items for coins with shopkeepers in town.
+    Shopkeepers sell from their stock at an item's full value and buy anything the player carries for half.
+*/
+use bracket_terminal::prelude::*;
+use hecs::{Entity, With, World};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actor::Player,
+    data_read::item_value,
+    item::{Inventory, Item},
+    messagelog::Message,
+    monster::Breed,
+    state::{RunState, State},
+};
+
+/// Coins the player starts a run with
+pub const STARTING_COINS: u32 = 20;
+
+/// Items a shopkeeper has for sale, they never run out
+pub struct Shop {
+    pub stock: Vec<String>,
+}
+
+/// Coins carried for trading
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Purse(pub u32);
+
+/// Where the player is in a shop's menu
+#[derive(Clone)]
+pub struct ShopMenu {
+    pub keeper: Entity,
+    pub selling: bool, // false while looking at the shop's stock, true while looking at the player's items
+    pub selected: usize,
+}
+
+/// Opens the shop of a being, None if it has nothing to trade
+pub fn open_shop(world: &World, keeper: Entity) -> Option<ShopMenu> {
+    world.get::<&Shop>(keeper).ok()?;
+    Some(ShopMenu {
+        keeper,
+        selling: false,
+        selected: 0,
+    })
+}
+
+/// What a shopkeeper will pay for an item
+pub fn sell_price(name: &str) -> u32 {
+    (item_value(name) / 2).max(1)
+}
+
+/// Lets the player browse and trade until they leave the shop
+pub fn run_shop_systems(state: &mut State, ctx: &mut BTerm, mut menu: ShopMenu) -> RunState {
+    let listing = shop_listing(&state.world, &menu);
+    if let Some(key) = ctx.key {
+        match key {
+            VirtualKeyCode::Escape => return RunState::InGame,
+            VirtualKeyCode::Tab => {
+                menu.selling = !menu.selling;
+                menu.selected = 0;
+            }
+            VirtualKeyCode::K | VirtualKeyCode::Up => menu.selected = menu.selected.saturating_sub(1),
+            VirtualKeyCode::J | VirtualKeyCode::Down => {
+                menu.selected = (menu.selected + 1).min(listing.len().saturating_sub(1));
+            }
+            VirtualKeyCode::Return => {
+                if let Some((name, price)) = listing.get(menu.selected) {
+                    match menu.selling {
+                        true => sell(state, name, *price),
+                        false => buy(state, name, *price),
+                    }
+                }
+                let remaining = shop_listing(&state.world, &menu).len();
+                menu.selected = menu.selected.min(remaining.saturating_sub(1));
+            }
+            _ => {}
+        }
+    }
+
+    draw_shop(ctx, state, &menu);
+    RunState::Shop(menu)
+}
+
+/// The items and prices shown on the current page of the menu
+fn shop_listing(world: &World, menu: &ShopMenu) -> Vec<(String, u32)> {
+    if menu.selling {
+        return match world.query::<With<&Inventory, &Player>>().iter().next() {
+            Some((_, inventory)) => inventory
+                .counts()
+                .into_iter()
+                .map(|(name, _)| {
+                    let price = sell_price(&name);
+                    (name, price)
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+    }
+
+    match world.get::<&Shop>(menu.keeper) {
+        Ok(shop) => shop.stock.iter().map(|name| (name.clone(), item_value(name))).collect(),
+        Err(..) => Vec::new(),
+    }
+}
+
+fn buy(state: &mut State, name: &str, price: u32) {
+    let turn = state.turn_counter;
+    if let Some((_, (purse, inventory))) = state
+        .world
+        .query_mut::<With<(&mut Purse, &mut Inventory), &Player>>()
+        .into_iter()
+        .next()
+    {
+        if purse.0 < price {
+            state
+                .message_log
+                .push(Message::new(format!("You can't afford the {name}"), turn));
+            return;
+        }
+        purse.0 -= price;
+        inventory.items.push(Item::new(name));
+        state
+            .message_log
+            .push(Message::new(format!("You bought {name} for {price} coins"), turn));
+    }
+}
+
+fn sell(state: &mut State, name: &str, price: u32) {
+    let turn = state.turn_counter;
+    if let Some((_, (purse, inventory))) = state
+        .world
+        .query_mut::<With<(&mut Purse, &mut Inventory), &Player>>()
+        .into_iter()
+        .next()
+    {
+        if let Some(idx) = inventory.items.iter().position(|item| item.name == name) {
+            inventory.items.remove(idx);
+            purse.0 += price;
+            state
+                .message_log
+                .push(Message::new(format!("You sold {name} for {price} coins"), turn));
+        }
+    }
+}
+
+fn draw_shop(ctx: &mut BTerm, state: &State, menu: &ShopMenu) {
+    let (x, y, width, height) = (25, 12, 50, 30);
+    let keeper = match state.world.get::<&Breed>(menu.keeper) {
+        Ok(breed) => breed.name.clone(),
+        Err(..) => "Shop".to_string(),
+    };
+    let coins = match state.world.query::<With<&Purse, &Player>>().iter().next() {
+        Some((_, purse)) => purse.0,
+        None => 0,
+    };
+
+    ctx.draw_box(x, y, width, height, WHITE, BLACK);
+    ctx.print_color(x + 2, y, YELLOW, BLACK, format!(" {keeper} "));
+    let (buy_color, sell_color) = match menu.selling {
+        true => (GREY, WHITE),
+        false => (WHITE, GREY),
+    };
+    ctx.print_color(x + 2, y + 2, buy_color, BLACK, "Buy");
+    ctx.print_color(x + 8, y + 2, sell_color, BLACK, "Sell");
+    ctx.print(x + 30, y + 2, format!("Coins: {coins}"));
+
+    let listing = shop_listing(&state.world, menu);
+    if listing.is_empty() {
+        ctx.print_color(x + 2, y + 4, GREY, BLACK, "Nothing to trade");
+    }
+    for (offset, (name, price)) in listing.iter().enumerate().take(height - 6) {
+        let color = match offset == menu.selected {
+            true => LIGHT_BLUE,
+            false => WHITE,
+        };
+        ctx.print_color(x + 2, y + 4 + offset, color, BLACK, name);
+        ctx.print_color(x + 40, y + 4 + offset, color, BLACK, format!("{price:>4}c"));
+    }
+
+    ctx.print_color(x + 2, y + height, GREY, BLACK, " Enter trade  Tab buy/sell  Esc leave ");
+}
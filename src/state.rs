@@ -7,15 +7,30 @@ use crate::{
     actor::render_entities,
     combat::destroy_dead_beings,
     config::Config,
+    consumable::{run_use_menu_systems, UseMenu},
+    crafting::{run_crafting_systems, CraftMenu},
+    dialogue::{run_dialogue_systems, Conversation},
+    energy::{player_action_time, WAIT_COST},
+    fluid::flow_fluids,
     fov::update_vision,
     gui::draw_gui,
+    hunger::tick_hunger,
     input::{handle_player_action, player_input},
+    lighting::{burn_torch, update_lighting},
     map::{render_map, Map},
     menu::{run_menu_systems, MenuIndex},
     messagelog::Message,
     monster::handle_monster_turns,
+    morgue::{bury_player, player_is_dead, run_game_over_systems, RunRecord},
+    navigation::{update_navigation, Navigation},
+    noise::{listen_for_noises, Noise},
+    oxygen::breathe,
+    ranged::{run_targeting_systems, Targeting},
+    rest::{player_health, regenerate, rest_turn},
     save_system::save_game,
+    shop::{run_shop_systems, ShopMenu},
     start_new_game,
+    status::tick_status_effects,
     worldgen::move_to_new_floor,
 };
 
@@ -29,6 +44,9 @@ pub struct State {
     pub runstate: RunState,
     pub config: Config,
     pub turn_counter: usize,
+    pub record: RunRecord,
+    pub noises: Vec<Noise>, // noises made since the beings last listened
+    pub navigation: Navigation,
 
     pub visible: Vec<bool>, // Player's visibility
 }
@@ -39,11 +57,18 @@ pub enum RunState {
     MainMenu(MenuIndex),
     NextLevel(usize),
     SaveGame,
+    GameOver,
+    Dialogue(Conversation),
+    Shop(ShopMenu),
+    Resting(u32), // the player's health after the last turn rested
+    Targeting(Targeting),
+    UseItem(UseMenu),
+    Crafting(CraftMenu),
 }
 
 pub enum PlayerResponse {
     StateChange(RunState),
-    TurnAdvance,
+    TurnAdvance(u32), // the time cost of the action taken
     Waiting,
 }
 
@@ -60,6 +85,9 @@ impl State {
                 Message::new("This is an alpha build from April 2023".to_string(), 0),
             ],
             turn_counter: 0,
+            record: RunRecord::default(),
+            noises: Vec::new(),
+            navigation: Navigation::empty(),
             generated_maps: HashMap::new(),
             visible: vec![false; config.map_x * config.map_y],
         }
@@ -79,6 +107,9 @@ impl State {
                 Message::new("This is a dev build from April 2023".to_string(), 0),
             ],
             turn_counter: 0,
+            record: RunRecord::default(),
+            noises: Vec::new(),
+            navigation: Navigation::empty(),
             generated_maps: HashMap::new(),
             visible: vec![false; config.map_x * config.map_y],
         }
@@ -93,6 +124,7 @@ impl State {
     fn run_continuous_systems(&mut self, ctx: &mut BTerm) {
         ctx.cls();
         update_vision(self);
+        update_lighting(self);
 
         render_map(ctx, &self.map, &self.config, &self.visible, &self.map.discovered);
         render_entities(ctx, self);
@@ -104,9 +136,46 @@ impl State {
         destroy_dead_beings(&mut self.world, &mut self.map);
     }
 
+    /// Passes a turn for an action of the player's that took some time
+    /// Returns true if the player didn't survive it
+    fn advance_turn(&mut self, cost: u32) -> bool {
+        self.turn_counter += 1;
+        self.map.light_dirty = true;
+        let time = player_action_time(&self.world, cost);
+        self.run_pre_response_systems();
+        self.run_response_systems(time);
+
+        if player_is_dead(self) {
+            bury_player(self);
+            return true;
+        }
+        false
+    }
+
+    /// Works out where a menu that may take the player's turn leads, back to the game once the turn is over
+    fn resolve_menu_response(&mut self, response: PlayerResponse) -> RunState {
+        match response {
+            PlayerResponse::StateChange(new_state) => new_state,
+            PlayerResponse::TurnAdvance(cost) => match self.advance_turn(cost) {
+                true => RunState::GameOver,
+                false => RunState::InGame,
+            },
+            PlayerResponse::Waiting => RunState::InGame,
+        }
+    }
+
     /// Response systems are ran after a player inputs something that progresses a turn
-    fn run_response_systems(&mut self) {
-        handle_monster_turns(self);
+    /// Time is how long the player's action took
+    fn run_response_systems(&mut self, time: u32) {
+        tick_status_effects(self);
+        regenerate(self);
+        tick_hunger(self);
+        burn_torch(self);
+        flow_fluids(self);
+        breathe(self);
+        listen_for_noises(self);
+        update_navigation(self);
+        handle_monster_turns(self, time);
     }
 }
 
@@ -123,10 +192,11 @@ impl GameState for State {
                         ctx.cls();
                         newstate = new_state;
                     }
-                    PlayerResponse::TurnAdvance => {
-                        self.turn_counter += 1;
-                        self.run_pre_response_systems();
-                        self.run_response_systems();
+                    PlayerResponse::TurnAdvance(cost) => {
+                        let died = self.advance_turn(cost);
+                        if died {
+                            newstate = RunState::GameOver;
+                        }
                     }
                     _ => {}
                 }
@@ -143,6 +213,43 @@ impl GameState for State {
                 self.clean_up();
                 newstate = RunState::MainMenu(MenuIndex(0));
             }
+            RunState::GameOver => {
+                newstate = run_game_over_systems(self, ctx);
+            }
+            RunState::Dialogue(conversation) => {
+                self.run_continuous_systems(ctx);
+                newstate = run_dialogue_systems(self, ctx, conversation);
+            }
+            RunState::Shop(menu) => {
+                self.run_continuous_systems(ctx);
+                newstate = run_shop_systems(self, ctx, menu);
+            }
+            RunState::Resting(last_health) => {
+                self.run_continuous_systems(ctx);
+                match rest_turn(self, ctx, last_health) {
+                    Some(reason) => {
+                        self.message_log.push(Message::new(reason, self.turn_counter));
+                        newstate = RunState::InGame;
+                    }
+                    None if self.advance_turn(WAIT_COST) => newstate = RunState::GameOver,
+                    None => newstate = RunState::Resting(player_health(self).unwrap_or(last_health)),
+                }
+            }
+            RunState::Targeting(targeting) => {
+                self.run_continuous_systems(ctx);
+                let response = run_targeting_systems(self, ctx, targeting);
+                newstate = self.resolve_menu_response(response);
+            }
+            RunState::UseItem(menu) => {
+                self.run_continuous_systems(ctx);
+                let response = run_use_menu_systems(self, ctx, menu);
+                newstate = self.resolve_menu_response(response);
+            }
+            RunState::Crafting(menu) => {
+                self.run_continuous_systems(ctx);
+                let response = run_crafting_systems(self, ctx, menu);
+                newstate = self.resolve_menu_response(response);
+            }
         }
 
         self.runstate = newstate;
@@ -0,0 +1,252 @@
+/*  Status effects are temporary conditions on a being, like poison from a centipede's bite.
+    Each one lasts for some number of turns and is ticked once a turn, harmful ones can be stacked up by
+    being hit again while others only have their duration refreshed.
+*/
+use hecs::{Entity, With, World};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{actor::Player, combat::CombatStats, fov::ViewShed, messagelog::Message, morgue::player_is_dead, State};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusKind {
+    Poisoned,     // loses potency health every turn
+    Bleeding,     // loses potency health every turn
+    Stunned,      // loses their turns
+    Slowed,       // actions take twice as long
+    Blinded,      // can only see the tiles next to them
+    Regenerating, // heals potency health every turn
+}
+
+/// How a status is combined with one of the same kind that is already on a being
+enum Stacking {
+    Intensity, // potencies add up, the longer duration is kept
+    Duration,  // durations add up
+    Refresh,   // the longer duration is kept
+}
+
+impl StatusKind {
+    fn stacking(&self) -> Stacking {
+        match self {
+            StatusKind::Poisoned | StatusKind::Bleeding => Stacking::Intensity,
+            StatusKind::Regenerating => Stacking::Duration,
+            StatusKind::Stunned | StatusKind::Slowed | StatusKind::Blinded => Stacking::Refresh,
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            StatusKind::Poisoned => "poisoned",
+            StatusKind::Bleeding => "bleeding",
+            StatusKind::Stunned => "stunned",
+            StatusKind::Slowed => "slowed",
+            StatusKind::Blinded => "blinded",
+            StatusKind::Regenerating => "regenerating",
+        }
+    }
+
+    /// Three letter tag shown in the side panel
+    pub fn tag(&self) -> &'static str {
+        match self {
+            StatusKind::Poisoned => "PSN",
+            StatusKind::Bleeding => "BLD",
+            StatusKind::Stunned => "STN",
+            StatusKind::Slowed => "SLW",
+            StatusKind::Blinded => "BLN",
+            StatusKind::Regenerating => "RGN",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub duration: u32,
+    #[serde(default)]
+    pub potency: u32,
+}
+
+impl StatusEffect {
+    pub fn new(kind: StatusKind, duration: u32, potency: u32) -> Self {
+        Self {
+            kind,
+            duration,
+            potency,
+        }
+    }
+}
+
+/// Every status currently affecting a being
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatusEffects(pub Vec<StatusEffect>);
+
+impl StatusEffects {
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.0.iter().any(|effect| effect.kind == kind)
+    }
+
+    /// Adds a status, combining it with any of the same kind following that kind's stacking rule
+    pub fn add(&mut self, new: StatusEffect) {
+        let Some(existing) = self.0.iter_mut().find(|effect| effect.kind == new.kind) else {
+            self.0.push(new);
+            return;
+        };
+        match new.kind.stacking() {
+            Stacking::Intensity => {
+                existing.potency += new.potency;
+                existing.duration = existing.duration.max(new.duration);
+            }
+            Stacking::Duration => existing.duration += new.duration,
+            Stacking::Refresh => existing.duration = existing.duration.max(new.duration),
+        }
+    }
+}
+
+/// A status an attacker has a chance of inflicting whenever it lands a blow, chance is 0.0 to 1.0
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct OnHit {
+    #[serde(flatten)]
+    pub effect: StatusEffect,
+    pub chance: f32,
+}
+
+/// Statuses a being's attacks can inflict
+pub struct OnHitEffects(pub Vec<OnHit>);
+
+/// Gives a being a status, returns true if it is the player
+pub fn inflict(world: &mut World, target: Entity, effect: StatusEffect) -> bool {
+    let is_player = world.get::<&Player>(target).is_ok();
+    let updated = match world.get::<&mut StatusEffects>(target) {
+        Ok(mut effects) => {
+            effects.add(effect);
+            true
+        }
+        Err(..) => false,
+    };
+    if !updated {
+        let _ = world.insert_one(target, StatusEffects(vec![effect]));
+    }
+    if effect.kind == StatusKind::Blinded {
+        if let Ok(mut view) = world.get::<&mut ViewShed>(target) {
+            view.dirty = true;
+        }
+    }
+    is_player
+}
+
+/// Rolls for each of the attacker's on hit statuses after it has landed a blow on the defender
+pub fn inflict_on_hit(state: &mut State, attacker: Entity, defender: Entity) {
+    let on_hits = match state.world.get::<&OnHitEffects>(attacker) {
+        Ok(on_hits) => on_hits.0.clone(),
+        Err(..) => return,
+    };
+    let mut rng = rand::thread_rng();
+    for on_hit in on_hits {
+        if rng.gen::<f32>() >= on_hit.chance {
+            continue;
+        }
+        if inflict(&mut state.world, defender, on_hit.effect) {
+            state.message_log.push(Message::new(
+                format!("You are {}!", on_hit.effect.kind.describe()),
+                state.turn_counter,
+            ));
+        }
+    }
+}
+
+/// Whether the player is currently under a status
+pub fn player_has_status(world: &World, kind: StatusKind) -> bool {
+    match world.query::<With<&StatusEffects, &Player>>().iter().next() {
+        Some((_, effects)) => effects.has(kind),
+        None => false,
+    }
+}
+
+/// Applies every status for a turn and wears them down, the player is told when theirs wear off
+pub fn tick_status_effects(state: &mut State) {
+    let turn = state.turn_counter;
+    let mut killer = None;
+    for (_, (effects, stats, view, player)) in state.world.query_mut::<(
+        &mut StatusEffects,
+        &mut CombatStats,
+        Option<&mut ViewShed>,
+        Option<&Player>,
+    )>() {
+        for effect in effects.0.iter_mut() {
+            match effect.kind {
+                StatusKind::Poisoned | StatusKind::Bleeding => {
+                    stats.health = stats.health.saturating_sub(effect.potency);
+                    if player.is_some() && stats.health == 0 {
+                        killer = Some(effect.kind);
+                    }
+                }
+                StatusKind::Regenerating if stats.health > 0 => {
+                    stats.health = (stats.health + effect.potency).min(stats.max_health);
+                }
+                _ => {}
+            }
+            effect.duration = effect.duration.saturating_sub(1);
+        }
+
+        let mut worn_off = Vec::new();
+        effects.0.retain(|effect| match effect.duration {
+            0 => {
+                worn_off.push(effect.kind);
+                false
+            }
+            _ => true,
+        });
+        if worn_off.contains(&StatusKind::Blinded) {
+            if let Some(view) = view {
+                view.dirty = true;
+            }
+        }
+        if player.is_some() {
+            for kind in worn_off {
+                state
+                    .message_log
+                    .push(Message::new(format!("You are no longer {}", kind.describe()), turn));
+            }
+        }
+    }
+
+    if player_is_dead(state) && state.record.killer.is_none() {
+        if let Some(kind) = killer {
+            let cause = match kind {
+                StatusKind::Bleeding => "blood loss",
+                _ => "poison",
+            };
+            state.record.killer = Some(cause.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn harmful_statuses_stack_up() {
+        let mut effects = StatusEffects::default();
+        effects.add(StatusEffect::new(StatusKind::Poisoned, 5, 1));
+        effects.add(StatusEffect::new(StatusKind::Poisoned, 3, 2));
+        assert_eq!(effects.0, vec![StatusEffect::new(StatusKind::Poisoned, 5, 3)]);
+    }
+
+    #[test]
+    fn other_statuses_stack_by_duration() {
+        let mut effects = StatusEffects::default();
+        effects.add(StatusEffect::new(StatusKind::Stunned, 2, 0));
+        effects.add(StatusEffect::new(StatusKind::Stunned, 1, 0));
+        effects.add(StatusEffect::new(StatusKind::Regenerating, 4, 1));
+        effects.add(StatusEffect::new(StatusKind::Regenerating, 4, 1));
+        assert_eq!(
+            effects.0,
+            vec![
+                StatusEffect::new(StatusKind::Stunned, 2, 0),
+                StatusEffect::new(StatusKind::Regenerating, 8, 1)
+            ]
+        );
+    }
+}
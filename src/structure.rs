@@ -0,0 +1,276 @@
+/*  Structure.rs models how well the cave ceiling is held up.
+    Natural caverns have long since settled so only tunnels dug out by hand are able to cave in.
+*/
+use bracket_pathfinding::prelude::Point;
+use hecs::{Entity, With, World};
+
+use crate::{
+    actor::{Player, Position},
+    combat::CombatStats,
+    data_read::named_tile,
+    fov::ViewShed,
+    map::{Destructible, Map, TileType},
+    messagelog::Message,
+    monster::Breed,
+    morgue::player_is_dead,
+    status::{inflict, StatusEffect, StatusKind},
+    State,
+};
+
+/// Damage dealt to any being standing under a tile as it collapses
+pub const CAVE_IN_DAMAGE: u32 = 4;
+/// Turns a being caught under a cave-in is left dazed for
+const CAVE_IN_STUN: u32 = 2;
+/// An excavated tile with this many or fewer solid neighbors will collapse
+const COLLAPSE_SUPPORT: usize = 1;
+/// An excavated tile with this many or fewer solid neighbors is at risk of collapsing
+const UNSTABLE_SUPPORT: usize = 3;
+/// How far from a freshly mined tile the ceiling is checked
+const CAVE_IN_RADIUS: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stability {
+    Stable,
+    Unstable,
+    Collapsing,
+}
+
+impl Stability {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Stability::Stable => "Stable",
+            Stability::Unstable => "Unstable",
+            Stability::Collapsing => "Collapsing",
+        }
+    }
+}
+
+/// Counts the solid tiles surrounding an index that are holding up the ceiling
+/// Anything past the edge of the map is solid rock
+pub fn support_at(map: &Map, idx: usize) -> usize {
+    let center = map.idx_to_point(idx);
+    let mut support = 0;
+    for x in (center.x - 1)..=(center.x + 1) {
+        for y in (center.y - 1)..=(center.y + 1) {
+            let pt = Point::new(x, y);
+            if pt == center {
+                continue;
+            }
+            if !map.within_bounds(pt) || is_supporting(map, pt.to_index(map.width)) {
+                support += 1;
+            }
+        }
+    }
+    support
+}
+
+fn is_supporting(map: &Map, idx: usize) -> bool {
+    matches!(map.tiles[idx].tile_type, TileType::Wall | TileType::Rubble)
+}
+
+/// How close the ceiling above a tile is to falling in
+pub fn stability_at(map: &Map, idx: usize) -> Stability {
+    if !map.excavated.get(idx).copied().unwrap_or(false) {
+        return Stability::Stable;
+    }
+
+    match support_at(map, idx) {
+        s if s <= COLLAPSE_SUPPORT => Stability::Collapsing,
+        s if s <= UNSTABLE_SUPPORT => Stability::Unstable,
+        _ => Stability::Stable,
+    }
+}
+
+/// The worst stability of the tile at a point and the tiles next to it
+pub fn stability_near(map: &Map, center: Point) -> Stability {
+    let mut worst = Stability::Stable;
+    for x in (center.x - 1)..=(center.x + 1) {
+        for y in (center.y - 1)..=(center.y + 1) {
+            let pt = Point::new(x, y);
+            if !map.within_bounds(pt) {
+                continue;
+            }
+            match stability_at(map, pt.to_index(map.width)) {
+                Stability::Stable => {}
+                Stability::Unstable if worst == Stability::Stable => worst = Stability::Unstable,
+                Stability::Unstable => {}
+                Stability::Collapsing => return Stability::Collapsing,
+            }
+        }
+    }
+    worst
+}
+
+/// Checks the ceiling around the player after they have mined
+pub fn check_cave_in(state: &mut State) {
+    let player_pos = match state.world.query::<With<&Position, &Player>>().iter().next() {
+        Some((_, pos)) => pos.0,
+        None => return,
+    };
+    cave_in(
+        &mut state.map,
+        &mut state.world,
+        player_pos,
+        &mut state.message_log,
+        state.turn_counter,
+    );
+
+    if player_is_dead(state) && state.record.killer.is_none() {
+        state.record.killer = Some("a cave-in".to_string());
+    }
+}
+
+/// Collapses any excavated tiles near a point that have lost too much support into rubble
+/// Beings caught underneath are crushed by the falling rock and pushed out onto open ground
+pub fn cave_in(map: &mut Map, world: &mut World, around: Point, message_log: &mut Vec<Message>, turn: usize) {
+    // gather every tile first so the rubble from one collapse doesn't prop up its neighbors
+    let mut collapsing = Vec::new();
+    for x in (around.x - CAVE_IN_RADIUS)..=(around.x + CAVE_IN_RADIUS) {
+        for y in (around.y - CAVE_IN_RADIUS)..=(around.y + CAVE_IN_RADIUS) {
+            let pt = Point::new(x, y);
+            if !map.within_bounds(pt) {
+                continue;
+            }
+            let idx = pt.to_index(map.width);
+            if stability_at(map, idx) == Stability::Collapsing {
+                collapsing.push(idx);
+            }
+        }
+    }
+
+    if collapsing.is_empty() {
+        return;
+    }
+    message_log.push(Message::new("The ceiling caves in!".to_string(), turn));
+
+    map.air_dirty = true;
+    let mut buried = Vec::new();
+    for &idx in collapsing.iter() {
+        buried.push((idx, map.tiles[idx]));
+        map.tiles[idx] = named_tile("Rubble");
+        map.destructibles[idx] = Some(Destructible::Tile { max_hp: 1, hp: 1 });
+        map.excavated[idx] = false;
+    }
+
+    for (idx, old_tile) in buried {
+        if let Some(being) = map.beings[idx] {
+            if let Ok(mut crushed) = world.query_one::<(&mut CombatStats, Option<&Breed>)>(being) {
+                if let Some((stats, breed)) = crushed.get() {
+                    stats.health = stats.health.saturating_sub(CAVE_IN_DAMAGE);
+                    let name = match breed {
+                        Some(breed) => breed.name.clone(),
+                        None => "Player".to_string(),
+                    };
+                    message_log.push(Message::new(
+                        format!("{name} took {CAVE_IN_DAMAGE} hp from falling rock"),
+                        turn,
+                    ));
+                }
+            }
+            inflict(world, being, StatusEffect::new(StatusKind::Stunned, CAVE_IN_STUN, 0));
+
+            // with nowhere to be pushed the rock settles around them and the tile stays open
+            if !dig_out(map, world, being, idx) {
+                map.tiles[idx] = old_tile;
+                map.destructibles[idx] = None;
+                map.excavated[idx] = true;
+            }
+        }
+    }
+}
+
+/// Pushes a being caught under rubble onto an open tile next to it, returns true if it was moved
+fn dig_out(map: &mut Map, world: &mut World, being: Entity, idx: usize) -> bool {
+    let from = map.idx_to_point(idx);
+    let open = (-1..=1)
+        .flat_map(|x| (-1..=1).map(move |y| Point::new(from.x + x, from.y + y)))
+        .filter(|pt| map.within_bounds(*pt))
+        .map(|pt| pt.to_index(map.width))
+        .find(|&dest| !map.tiles[dest].is_blocking && map.beings[dest].is_none() && map.destructibles[dest].is_none());
+    let Some(dest) = open else {
+        return false;
+    };
+
+    if let Ok((pos, view)) = world.query_one_mut::<(&mut Position, Option<&mut ViewShed>)>(being) {
+        pos.0 = map.idx_to_point(dest);
+        if let Some(view) = view {
+            view.dirty = true;
+        }
+    }
+    map.beings[idx] = None;
+    map.beings[dest] = Some(being);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::WorldTile;
+
+    /// A 7x7 block of solid rock
+    fn solid_map() -> Map {
+        Map::test_strip(7, 7, &[TileType::Wall; 49])
+    }
+
+    fn dig(map: &mut Map, x: usize, y: usize) {
+        let idx = map.xy_to_idx(x, y);
+        map.tiles[idx] = WorldTile::test(TileType::Floor);
+        map.excavated[idx] = true;
+    }
+
+    #[test]
+    fn narrow_tunnel_is_stable() {
+        let mut map = solid_map();
+        for x in 1..6 {
+            dig(&mut map, x, 3);
+        }
+        assert_eq!(support_at(&map, map.xy_to_idx(3, 3)), 6);
+        assert_eq!(stability_near(&map, Point::new(3, 3)), Stability::Stable);
+    }
+
+    #[test]
+    fn wide_dig_collapses() {
+        let mut map = solid_map();
+        for x in 2..5 {
+            for y in 2..5 {
+                dig(&mut map, x, y);
+            }
+        }
+        assert_eq!(stability_at(&map, map.xy_to_idx(3, 3)), Stability::Collapsing);
+        assert_eq!(stability_at(&map, map.xy_to_idx(3, 2)), Stability::Unstable);
+        assert_eq!(stability_at(&map, map.xy_to_idx(2, 2)), Stability::Stable);
+    }
+
+    #[test]
+    fn natural_caverns_never_collapse() {
+        let mut map = solid_map();
+        for idx in 0..map.tiles.len() {
+            map.tiles[idx] = WorldTile::test(TileType::Floor);
+        }
+        assert_eq!(stability_at(&map, map.xy_to_idx(3, 3)), Stability::Stable);
+    }
+
+    #[test]
+    fn cave_in_pushes_beings_out_of_the_rubble() {
+        let mut map = solid_map();
+        for x in 2..5 {
+            for y in 2..5 {
+                dig(&mut map, x, y);
+            }
+        }
+        let mut world = World::new();
+        let center = Point::new(3, 3);
+        let center_idx = map.xy_to_idx(3, 3);
+        let being = world.spawn((Position(center), CombatStats::new(20, 1, 1)));
+        map.beings[center_idx] = Some(being);
+
+        cave_in(&mut map, &mut world, center, &mut Vec::new(), 0);
+
+        let pos = world.get::<&Position>(being).unwrap().0;
+        let idx = pos.to_index(map.width);
+        assert_ne!(pos, center);
+        assert!(!map.tiles[idx].is_blocking);
+        assert_eq!(map.beings[idx], Some(being));
+        assert_eq!(map.beings[center_idx], None);
+    }
+}
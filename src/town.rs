@@ -0,0 +1,138 @@
+/*  Towns are special floors found at set depths, a safe place to rest and trade.
+    The town sits in one wide cavern with its buildings stamped in from text prefabs.
+    Letters in a prefab mark where residents live, they are spawned there whenever the floor is furnished.
+*/
+use bracket_pathfinding::prelude::Point;
+use bracket_random::prelude::RandomNumberGenerator;
+
+use crate::{
+    actor::Position,
+    data_read::named_tile,
+    map::{FloorKind, Map, Resident},
+    oxygen::settle_air,
+    prefab::{load_text_prefab, Prefab},
+    worldgen::WorldRoom,
+};
+
+/// Buildings of the town and where their top left corners go
+const BUILDINGS: [(&str, usize, usize); 6] = [
+    ("shop", 44, 20),
+    ("smithy", 45, 46),
+    ("house", 22, 28),
+    ("house", 70, 28),
+    ("house", 32, 44),
+    ("house", 60, 44),
+];
+
+/// Who lives at each kind of marker in a building prefab
+fn resident_for(marker: char) -> Option<&'static str> {
+    match marker {
+        'S' => Some("Shopkeeper"),
+        'N' => Some("NPC Dude"),
+        _ => None,
+    }
+}
+
+/// What stands at each kind of fixture marker in a building prefab
+fn fixture_for(marker: char) -> Option<&'static str> {
+    match marker {
+        'W' => Some("Workbench"),
+        'F' => Some("Forge"),
+        _ => None,
+    }
+}
+
+/// Generates a town floor, returns the map and where the player arrives from above
+pub fn generate_town(seed: u64, depth: usize) -> (Map, Position) {
+    let width = 100;
+    let height = 70;
+    let mut map = Map {
+        tiles: vec![named_tile("Stone Wall"); width * height],
+        beings: vec![None; width * height],
+        discovered: vec![false; width * height],
+        excavated: vec![false; width * height],
+        destructibles: vec![None; width * height],
+        air: Vec::new(),
+        air_dirty: false,
+        light: Vec::new(),
+        light_dirty: false,
+        kind: FloorKind::Cave,
+        fluids: Vec::new(),
+        breakables: None,
+        ground_items: Vec::new(),
+        rooms: Vec::new(),
+        width,
+        height,
+        depth,
+    };
+    let mut rng = RandomNumberGenerator::seeded(seed + depth as u64);
+
+    carve_cavern(&mut map, &mut rng);
+
+    let (mut residents, mut fixtures) = (Vec::new(), Vec::new());
+    for (name, x, y) in BUILDINGS {
+        let building = load_text_prefab(name);
+        let (living, fixed) = stamp_building(&mut map, &building, Point::new(x, y));
+        residents.extend(living);
+        fixtures.extend(fixed);
+    }
+
+    let up_stairs = map.xy_to_idx(14, 35);
+    let down_stairs = map.xy_to_idx(86, 35);
+    map.tiles[up_stairs] = named_tile("Up Stairs");
+    map.tiles[down_stairs] = named_tile("Down Stairs");
+
+    let mut square = WorldRoom { tiles: Vec::new() };
+    for (idx, tile) in map.tiles.iter().enumerate() {
+        if !tile.is_blocking {
+            square.tiles.push(map.idx_to_point(idx));
+        }
+    }
+    map.rooms.push(square);
+    map.kind = FloorKind::Town { residents, fixtures };
+    settle_air(&mut map);
+
+    let arrival = map.idx_to_pos(up_stairs);
+    (map, arrival)
+}
+
+/// Hollows out a wide oval cavern in the middle of the map with a slightly ragged edge
+fn carve_cavern(map: &mut Map, rng: &mut RandomNumberGenerator) {
+    let center = Point::new(map.width / 2, map.height / 2);
+    let (radius_x, radius_y) = (40.0, 25.0);
+    for x in 1..map.width - 1 {
+        for y in 1..map.height - 1 {
+            let dx = (x as f32 - center.x as f32) / radius_x;
+            let dy = (y as f32 - center.y as f32) / radius_y;
+            let edge = 1.0 - rng.range(0, 8) as f32 / 100.0;
+            if dx * dx + dy * dy <= edge {
+                let idx = map.xy_to_idx(x, y);
+                map.tiles[idx] = named_tile("Stone Floor");
+            }
+        }
+    }
+}
+
+/// Copies a building onto the map, returns the residents and fixtures marked inside of it
+fn stamp_building(map: &mut Map, building: &Prefab, corner: Point) -> (Vec<Resident>, Vec<Resident>) {
+    for y in 0..building.height {
+        for x in 0..building.width {
+            let idx = map.xy_to_idx(corner.x as usize + x, corner.y as usize + y);
+            map.tiles[idx] = building.structure[x + y * building.width];
+        }
+    }
+
+    let marked = |lookup: fn(char) -> Option<&'static str>| {
+        building
+            .markers
+            .iter()
+            .filter_map(|(spot, marker)| {
+                lookup(*marker).map(|name| Resident {
+                    name: name.to_string(),
+                    home: corner + *spot,
+                })
+            })
+            .collect()
+    };
+    (marked(resident_for), marked(fixture_for))
+}
@@ -0,0 +1,150 @@
+/*  Water fills the low parts of the caves, lakes pool in the larger caverns and rivers cut right through the rock.
+    Anything can swim across it given the time, though heavy things sink to the bottom and nobody can breathe
+    under it. Some beings won't set foot in water while others never leave it.
+*/
+use bracket_pathfinding::prelude::{Algorithm2D, BaseMap, DistanceAlg, SmallVec};
+use bracket_terminal::prelude::Point;
+use hecs::With;
+use serde::Deserialize;
+
+use crate::{
+    actor::{Player, Position},
+    combat::CombatStats,
+    data_read::{item_heavy, named_item_builder, ENTITY_DB},
+    energy::SWIM_COST,
+    equipment::{strip_gear, Equipment},
+    item::{Inventory, Item},
+    map::{Map, TileType},
+    messagelog::Message,
+    State,
+};
+
+/// How a being takes to water, beings without one swim only when they have to
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WaterAffinity {
+    Avoids,  // never steps into water
+    Prefers, // swims freely and won't leave the water once in it
+}
+
+/// A view of the map as a being with some affinity for water sees it
+pub struct WadingMap<'a>(pub &'a Map, pub WaterAffinity);
+
+pub fn is_water(map: &Map, idx: usize) -> bool {
+    map.tiles.get(idx).is_some_and(|tile| tile.tile_type == TileType::Water)
+}
+
+/// Whether a being is willing to step from one tile to another
+pub fn can_enter(map: &Map, from: usize, to: usize, affinity: Option<WaterAffinity>) -> bool {
+    match affinity {
+        Some(WaterAffinity::Avoids) => !is_water(map, to),
+        Some(WaterAffinity::Prefers) => is_water(map, to) || !is_water(map, from),
+        None => true,
+    }
+}
+
+/// Time taken by a step that ended on a tile, swimming is slow for anything not at home in the water
+pub fn swim_cost(map: &Map, to: usize, affinity: Option<WaterAffinity>, cost: u32) -> u32 {
+    match is_water(map, to) && affinity != Some(WaterAffinity::Prefers) {
+        true => cost.max(SWIM_COST),
+        false => cost,
+    }
+}
+
+/// Whether the player is swimming
+pub fn player_in_water(state: &State) -> bool {
+    match state.world.query::<With<&Position, &Player>>().iter().next() {
+        Some((_, pos)) => is_water(&state.map, pos.0.to_index(state.map.width)),
+        None => false,
+    }
+}
+
+/// Anything too heavy to swim with slips out of the player's pack or off their body and sinks where they are
+pub fn sink_heavy_items(state: &mut State) {
+    let Some((_, (pos, inventory, equipment, stats))) = state
+        .world
+        .query_mut::<With<(&Position, &mut Inventory, Option<&mut Equipment>, &mut CombatStats), &Player>>()
+        .into_iter()
+        .next()
+    else {
+        return;
+    };
+    let at = pos.0;
+    let (mut sunk, kept): (Vec<Item>, Vec<Item>) = inventory.items.drain(..).partition(|item| item_heavy(&item.name));
+    inventory.items = kept;
+    // worn gear is no lighter, it slips off and takes its bonus with it
+    if let Some(equipment) = equipment {
+        sunk.extend(strip_gear(equipment, stats, |item| item_heavy(&item.name)));
+    }
+
+    let mut names: Vec<String> = Vec::new();
+    for item in sunk {
+        let builder = named_item_builder(&ENTITY_DB.lock().unwrap(), &item.name, Position(at));
+        if let Some(mut eb) = builder {
+            state.world.spawn(eb.build());
+        }
+        if !names.contains(&item.name) {
+            names.push(item.name);
+        }
+    }
+    for name in names {
+        state.message_log.push(Message::new(
+            format!("Your {name} sinks to the bottom"),
+            state.turn_counter,
+        ));
+    }
+}
+
+impl BaseMap for WadingMap<'_> {
+    fn is_opaque(&self, idx: usize) -> bool {
+        self.0.is_opaque(idx)
+    }
+
+    fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
+        self.0
+            .get_available_exits(idx)
+            .into_iter()
+            .filter(|(next, _)| can_enter(self.0, idx, *next, Some(self.1)))
+            .collect()
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        DistanceAlg::Pythagoras.distance2d(self.0.idx_to_point(idx1), self.0.idx_to_point(idx2))
+    }
+}
+
+impl Algorithm2D for WadingMap<'_> {
+    fn dimensions(&self) -> Point {
+        Point::new(self.0.width, self.0.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 5x1 corridor with water in its last two tiles
+    fn shore() -> Map {
+        use TileType::{Floor, Water};
+        Map::test_strip(5, 1, &[Floor, Floor, Floor, Water, Water])
+    }
+
+    #[test]
+    fn affinity_decides_who_enters_water() {
+        let map = shore();
+        assert!(can_enter(&map, 2, 3, None));
+        assert!(!can_enter(&map, 2, 3, Some(WaterAffinity::Avoids)));
+        assert!(can_enter(&map, 2, 3, Some(WaterAffinity::Prefers)));
+        assert!(!can_enter(&map, 3, 2, Some(WaterAffinity::Prefers)));
+        assert!(can_enter(&map, 1, 2, Some(WaterAffinity::Prefers)));
+    }
+
+    #[test]
+    fn wading_map_keeps_to_the_shore() {
+        let map = shore();
+        let exits = WadingMap(&map, WaterAffinity::Avoids).get_available_exits(2);
+        assert_eq!(exits.iter().map(|(idx, _)| *idx).collect::<Vec<_>>(), vec![1]);
+        let exits = WadingMap(&map, WaterAffinity::Prefers).get_available_exits(3);
+        assert_eq!(exits.iter().map(|(idx, _)| *idx).collect::<Vec<_>>(), vec![4]);
+    }
+}
@@ -1,12 +1,17 @@
 use crate::actor::{Player, Position};
+use crate::breakable::Breakable;
 use crate::data_read::named_tile;
-use crate::map::{Destructible, Map, TileType, WorldTile, MAP_HEIGHT, MAP_WIDTH};
+use crate::fluid::{settled_fluids, Fluid, FluidKind, MAX_LEVEL};
+use crate::item::Item;
+use crate::map::{Destructible, FloorKind, Map, Resident, StoredBreakable, TileType, WorldTile, MAP_HEIGHT, MAP_WIDTH};
 use crate::map_scanning::{find_tile_from_type, pretty_walls};
 use crate::monster::Breed;
+use crate::oxygen::settle_air;
 use crate::prefab::{load_rex_room, xy_to_idx};
+use crate::town::generate_town;
 use crate::{furnish_map, State};
 use bracket_noise::prelude::*;
-use bracket_pathfinding::prelude::Point;
+use bracket_pathfinding::prelude::{DistanceAlg, Point};
 use bracket_random::prelude::*;
 use bracket_terminal::prelude::to_cp437;
 use hecs::{With, World};
@@ -14,6 +19,25 @@ use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
+const VENTS_PER_FLOOR: usize = 3;
+const FUNGUS_PATCHES: usize = 8;
+/// Floors this deep and beyond have lava pooling in them
+const LAVA_DEPTH: usize = 5;
+const LAVA_POOLS: usize = 2;
+/// Only rooms at least this big are roomy enough to walk around a pool of lava
+const LAVA_ROOM_SIZE: usize = 150;
+const LAKES: usize = 3;
+/// Rooms smaller than this are left dry rather than flooded wall to wall
+const LAKE_ROOM_SIZE: usize = 80;
+/// Chance of an underground river winding across a floor
+const RIVER_CHANCE: f32 = 0.5;
+/// Rivers keep at least this far from the top and bottom of the map
+const RIVER_MARGIN: i32 = 5;
+const AQUIFERS: usize = 4;
+const AQUIFER_RADIUS: i32 = 2;
+/// Chance of an aquifer on a floor deep enough for lava holding magma rather than water
+const MAGMA_CHANCE: f32 = 0.4;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorldRoom {
     pub tiles: Vec<Point>,
@@ -41,7 +65,16 @@ pub fn generate_map(seed: u64, depth: usize) -> (Map, Position) {
         tiles: vec![named_tile("Stone Wall"); width * height],
         beings: vec![None; width * height],
         discovered: vec![false; width * height],
+        excavated: vec![false; width * height],
+        kind: FloorKind::Cave,
+        fluids: Vec::new(),
+        breakables: None,
+        ground_items: Vec::new(),
         destructibles: vec![None; width * height],
+        air: Vec::new(),
+        air_dirty: false,
+        light: Vec::new(),
+        light_dirty: false,
         rooms: Vec::new(),
         width,
         height,
@@ -54,6 +87,17 @@ pub fn generate_map(seed: u64, depth: usize) -> (Map, Position) {
 
     cull_rooms(&mut map);
     remove_small_rooms(&mut map, 10);
+    fill_lakes(&mut map, &mut rng);
+    if rng.rand::<f32>() < RIVER_CHANCE {
+        carve_river(&mut map, &mut rng);
+    }
+    // nothing should be spawned into the water
+    let tiles = &map.tiles;
+    for room in map.rooms.iter_mut() {
+        room.tiles
+            .retain(|pt| tiles[pt.to_index(map.width)].tile_type != TileType::Water);
+    }
+    map.rooms.retain(|room| !room.tiles.is_empty());
 
     let player_spawn = if map.depth == 0 {
         create_entrance(&mut map, &mut rng)
@@ -63,10 +107,18 @@ pub fn generate_map(seed: u64, depth: usize) -> (Map, Position) {
 
     place_tile_in_random_room(&mut map, &mut rng, named_tile("Down Stairs"));
     brush_spawn(&mut map, &mut rng);
+    place_vents(&mut map, &mut rng);
+    grow_fungus(&mut map, &mut rng);
+    if map.depth >= LAVA_DEPTH {
+        pool_lava(&mut map, &mut rng);
+    }
+    map.fluids = settled_fluids(&map);
+    place_aquifers(&mut map, &mut rng);
 
     // After all wall placing
     cull_destructibles(&mut map);
     pretty_walls(&mut map);
+    settle_air(&mut map);
 
     (map, player_spawn)
 }
@@ -76,6 +128,7 @@ pub fn cull_destructibles(map: &mut Map) {
     for (idx, tile) in map.tiles.iter().enumerate() {
         match tile.tile_type {
             TileType::Wall => map.destructibles[idx] = Some(Destructible::Tile { max_hp: 3, hp: 3 }),
+            TileType::Rubble => map.destructibles[idx] = Some(Destructible::Tile { max_hp: 1, hp: 1 }),
             _ => continue,
         }
     }
@@ -83,8 +136,9 @@ pub fn cull_destructibles(map: &mut Map) {
 
 /// Setups the new floor and cleans up the old floor
 pub fn move_to_new_floor(state: &mut State, new_depth: usize) {
-    // clean up old monsters
+    // clean up old monsters, breakables and anything left on the ground
     despawn_beings(&mut state.world, &mut state.map);
+    despawn_furnishings(&mut state.world, &mut state.map);
 
     // Update map that player was previously on
     state.generated_maps.insert(state.map.depth, state.map.clone());
@@ -92,10 +146,13 @@ pub fn move_to_new_floor(state: &mut State, new_depth: usize) {
     // state.discovered = vec![false; state.discovered.len()];
 
     let (new_map, new_player_pos) = match state.generated_maps.get(&new_depth) {
+        None if state.config.town_depths.contains(&new_depth) => generate_town(state.config.world_seed, new_depth),
         None => generate_map(state.config.world_seed, new_depth),
         Some(map) => (map.clone(), find_position_of_stairs(state.map.depth, new_depth, map)),
     };
     state.map = new_map;
+    state.map.light_dirty = true;
+    state.record.deepest = state.record.deepest.max(new_depth);
 
     if let Some((_, player_pos)) = state
         .world
@@ -130,6 +187,46 @@ fn despawn_beings(world: &mut World, map: &mut Map) {
     }
 }
 
+/// Helper for despawning breakables and items lying on the ground, they are put back when the floor is revisited
+fn despawn_furnishings(world: &mut World, map: &mut Map) {
+    store_furnishings(world, map);
+
+    let mut entities_to_despawn = Vec::new();
+    for (entity, pos) in world.query::<With<&Position, &Breakable>>().iter() {
+        entities_to_despawn.push(entity);
+        map.destructibles[pos.0.to_index(map.width)] = None;
+    }
+    for (entity, _) in world.query::<With<&Position, &Item>>().iter() {
+        entities_to_despawn.push(entity);
+    }
+    for entity in entities_to_despawn {
+        if world.despawn(entity).is_err() {
+            println!("Did not despawn properly");
+        }
+    }
+}
+
+/// Remembers where the breakables and the items on the ground are so the floor can be furnished with them again
+pub fn store_furnishings(world: &World, map: &mut Map) {
+    let mut breakables = Vec::new();
+    for (_, (breakable, pos)) in world.query::<(&Breakable, &Position)>().iter() {
+        breakables.push(StoredBreakable {
+            name: breakable.name.clone(),
+            home: pos.0,
+            hp: Some(breakable.hp),
+        });
+    }
+    map.breakables = Some(breakables);
+
+    map.ground_items.clear();
+    for (_, (item, pos)) in world.query::<(&Item, &Position)>().iter() {
+        map.ground_items.push(Resident {
+            name: item.name.clone(),
+            home: pos.0,
+        });
+    }
+}
+
 /// Helper for moving between floors and getting the player's new position
 fn find_position_of_stairs(old_depth: usize, new_depth: usize, map: &Map) -> Position {
     if old_depth < new_depth {
@@ -145,22 +242,19 @@ fn find_position_of_stairs(old_depth: usize, new_depth: usize, map: &Map) -> Pos
 /// skips any special tiles in a room such as staircases
 #[allow(dead_code)]
 fn debug_map_rooms(map: &mut Map) {
-    let mut room_num = 0;
     let symbols = (b'0'..=b'9')
         .chain(b'a'..=b'z')
         .chain(b'A'..=b'Z')
         .map(char::from)
         .collect::<Vec<_>>();
 
-    for room in &mut map.rooms {
+    for (room_num, room) in map.rooms.iter_mut().enumerate() {
         for tile in &room.tiles {
             let glyph = map.tiles[tile.to_index(map.width)].sprite.glyph;
             if glyph == to_cp437('.') {
                 map.tiles[tile.to_index(map.width)].sprite.glyph = to_cp437(symbols[room_num]);
             }
         }
-
-        room_num += 1;
     }
 }
 
@@ -173,6 +267,148 @@ fn place_tile_in_random_room(map: &mut Map, rng: &mut RandomNumberGenerator, til
     map.idx_to_pos(tile_pos)
 }
 
+/// Opens a few vents on plain floor, they bring fresh air into the deeper caves
+fn place_vents(map: &mut Map, rng: &mut RandomNumberGenerator) {
+    for _ in 0..VENTS_PER_FLOOR {
+        let idx = random_room_point(map, rng).0.to_index(map.width);
+        if map.tiles[idx].tile_type == TileType::Floor {
+            map.tiles[idx] = named_tile("Air Vent");
+        }
+    }
+}
+
+/// Grows small patches of glowing fungus, the only light in the deep caves besides lava
+fn grow_fungus(map: &mut Map, rng: &mut RandomNumberGenerator) {
+    for _ in 0..FUNGUS_PATCHES {
+        let center = random_room_point(map, rng).0;
+        for neighbor in get_neighbors(center).into_iter().chain([center]) {
+            if !map.within_bounds(neighbor) || rng.rand::<f32>() > 0.5 {
+                continue;
+            }
+            let idx = neighbor.to_index(map.width);
+            if map.tiles[idx].tile_type == TileType::Floor {
+                map.tiles[idx] = named_tile("Glowing Fungus");
+            }
+        }
+    }
+}
+
+/// Pools lava in the open floor of a few large rooms, stairs and vents are left untouched
+fn pool_lava(map: &mut Map, rng: &mut RandomNumberGenerator) {
+    for _ in 0..LAVA_POOLS {
+        let (center, room_idx) = random_room_point(map, rng);
+        if map.rooms[room_idx].tiles.len() < LAVA_ROOM_SIZE {
+            continue;
+        }
+        let radius = rng.range(1, 3);
+        for x in (center.x - radius)..=(center.x + radius) {
+            for y in (center.y - radius)..=(center.y + radius) {
+                let pt = Point::new(x, y);
+                if !map.within_bounds(pt) || DistanceAlg::Pythagoras.distance2d(center, pt) > radius as f32 {
+                    continue;
+                }
+                let idx = pt.to_index(map.width);
+                if matches!(map.tiles[idx].tile_type, TileType::Floor | TileType::Brush) {
+                    map.tiles[idx] = named_tile("Lava");
+                }
+            }
+        }
+        // nothing should be spawned into the pool
+        let tiles = &map.tiles;
+        map.rooms[room_idx]
+            .tiles
+            .retain(|pt| !tiles[pt.to_index(map.width)].is_blocking);
+    }
+}
+
+/// Floods the floor of a few larger rooms with ragged edged lakes
+fn fill_lakes(map: &mut Map, rng: &mut RandomNumberGenerator) {
+    for _ in 0..LAKES {
+        let (center, room_idx) = random_room_point(map, rng);
+        if map.rooms[room_idx].tiles.len() < LAKE_ROOM_SIZE {
+            continue;
+        }
+        let radius = rng.range(2, 5);
+        for x in (center.x - radius)..=(center.x + radius) {
+            for y in (center.y - radius)..=(center.y + radius) {
+                let pt = Point::new(x, y);
+                let reach = radius as f32 + rng.rand::<f32>() - 0.5;
+                if !map.within_bounds(pt) || DistanceAlg::Pythagoras.distance2d(center, pt) > reach {
+                    continue;
+                }
+                let idx = pt.to_index(map.width);
+                if map.tiles[idx].tile_type == TileType::Floor {
+                    map.tiles[idx] = named_tile("Deep Water");
+                }
+            }
+        }
+    }
+}
+
+/// Winds a river from one side of the floor to the other, cutting through rock and caverns alike
+fn carve_river(map: &mut Map, rng: &mut RandomNumberGenerator) {
+    let lowest = map.height as i32 - RIVER_MARGIN - 2;
+    let mut y = rng.range(RIVER_MARGIN, lowest);
+    for x in 1..map.width as i32 - 1 {
+        // the banks are widened wherever the river bends so it never narrows to a diagonal
+        let last_y = y;
+        y = (y + rng.range(-1, 2)).clamp(RIVER_MARGIN, lowest);
+        let width = rng.range(1, 3);
+        for river_y in last_y.min(y)..=(last_y.max(y) + width) {
+            let idx = Point::new(x, river_y).to_index(map.width);
+            if matches!(map.tiles[idx].tile_type, TileType::Floor | TileType::Wall) {
+                map.tiles[idx] = named_tile("Deep Water");
+            }
+        }
+    }
+}
+
+/// Soaks a few pockets of solid rock with water, or magma on the deepest floors, ready to flood out when mined into
+fn place_aquifers(map: &mut Map, rng: &mut RandomNumberGenerator) {
+    for _ in 0..AQUIFERS {
+        let margin = AQUIFER_RADIUS + 2;
+        let center = Point::new(
+            rng.range(margin, map.width as i32 - margin),
+            rng.range(margin, map.height as i32 - margin),
+        );
+        let within = |reach: i32| -> Vec<usize> {
+            let mut tiles = Vec::new();
+            for x in (center.x - reach)..=(center.x + reach) {
+                for y in (center.y - reach)..=(center.y + reach) {
+                    let pt = Point::new(x, y);
+                    if DistanceAlg::Pythagoras.distance2d(center, pt) <= reach as f32 {
+                        tiles.push(pt.to_index(map.width));
+                    }
+                }
+            }
+            tiles
+        };
+        // the pocket has to be sealed in rock or it would drain before anyone found it
+        if !within(AQUIFER_RADIUS + 1)
+            .iter()
+            .all(|&idx| map.tiles[idx].tile_type == TileType::Wall && map.fluids[idx].is_none())
+        {
+            continue;
+        }
+
+        let kind = match map.depth >= LAVA_DEPTH && rng.rand::<f32>() < MAGMA_CHANCE {
+            true => FluidKind::Lava,
+            false => FluidKind::Water,
+        };
+        for idx in within(AQUIFER_RADIUS) {
+            map.tiles[idx] = named_tile(kind.rock());
+            map.fluids[idx] = Some(Fluid { kind, level: MAX_LEVEL });
+        }
+    }
+}
+
+/// A random point in a random room along with which room it's in
+fn random_room_point(map: &Map, rng: &mut RandomNumberGenerator) -> (Point, usize) {
+    let room_idx = rng.range(0, map.rooms.len());
+    let room_pos_idx = rng.range(0, map.rooms[room_idx].tiles.len());
+    (map.rooms[room_idx].tiles[room_pos_idx], room_idx)
+}
+
 fn create_caverns(map: &mut Map, seed: u64) {
     let cave_noise = cave_perlin(seed);
 
@@ -355,20 +591,20 @@ fn remove_small_rooms(map: &mut Map, min_size: usize) {
 }
 
 /// Checks neighbors and finds all points within a single continuous room
-fn flood_fill(starting: Point, map: &Map, visited: &mut Vec<bool>) -> WorldRoom {
+fn flood_fill(starting: Point, map: &Map, visited: &mut [bool]) -> WorldRoom {
     let mut room = WorldRoom::new();
     let mut unvisited = vec![starting];
 
     while let Some(pt) = unvisited.pop() {
         let idx = pt.to_index(map.width);
-        if visited[idx] == true {
+        if visited[idx] {
             continue;
         }
         visited[idx] = true;
         room.tiles.push(pt);
 
         let neighbors = get_neighbors(pt);
-        if neighbors.len() == 0 {
+        if neighbors.is_empty() {
             continue;
         }
 
@@ -390,7 +626,7 @@ fn flood_fill(starting: Point, map: &Map, visited: &mut Vec<bool>) -> WorldRoom
 }
 
 fn get_priority(vec: &mut VecDeque<(Point, i32)>) -> Option<(Point, i32)> {
-    vec.make_contiguous().sort_by(|x, y| x.1.cmp(&y.1));
+    vec.make_contiguous().sort_by_key(|x| x.1);
     vec.pop_front()
 }
 
@@ -414,6 +650,18 @@ fn is_valid_neighbor(x: i32, y: i32, starting: Point) -> bool {
     x < MAP_WIDTH as i32 && y < MAP_HEIGHT as i32 && y >= 0 && x >= 0 && Point::new(x, y).ne(&starting)
 }
 
+fn cave_perlin(seed: u64) -> FastNoise {
+    let mut noise = FastNoise::seeded(seed);
+    noise.set_noise_type(NoiseType::SimplexFractal);
+    noise.set_fractal_type(FractalType::FBM);
+    noise.set_fractal_octaves(2);
+    noise.set_fractal_gain(0.02);
+    noise.set_fractal_lacunarity(0.5);
+    noise.set_frequency(2.5);
+
+    noise
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -432,16 +680,23 @@ mod tests {
         ];
         assert_eq!(get_neighbors(Point::new(1, 1)), neighbors);
     }
-}
 
-fn cave_perlin(seed: u64) -> FastNoise {
-    let mut noise = FastNoise::seeded(seed);
-    noise.set_noise_type(NoiseType::SimplexFractal);
-    noise.set_fractal_type(FractalType::FBM);
-    noise.set_fractal_octaves(2);
-    noise.set_fractal_gain(0.02);
-    noise.set_fractal_lacunarity(0.5);
-    noise.set_frequency(2.5);
-
-    noise
+    #[test]
+    fn furnishings_are_remembered() {
+        let mut world = World::new();
+        let mut map = Map::empty();
+        world.spawn((Breakable::new("Crate", 2, Vec::new()), Position::new(3, 4)));
+        world.spawn((Item::new("Throwing Knife"), Position::new(5, 6)));
+
+        store_furnishings(&world, &mut map);
+
+        let breakables = map.breakables.expect("breakables are stored");
+        assert_eq!(breakables.len(), 1);
+        assert_eq!(
+            (breakables[0].name.as_str(), breakables[0].home, breakables[0].hp),
+            ("Crate", Point::new(3, 4), Some(2))
+        );
+        assert_eq!(map.ground_items.len(), 1);
+        assert_eq!(map.ground_items[0].home, Point::new(5, 6));
+    }
 }